@@ -0,0 +1,257 @@
+use std::io;
+use witd::{Format, PrettyPrint};
+
+/// The list of flags that may precede the script.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Flag {
+    DryRun,
+    File,
+    Format,
+    Once,
+    Output,
+    Strict,
+}
+
+impl Flag {
+    /// Attempts to parse the flag from a string.
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::values().into_iter().find(|f| f.pretty_print() == s)
+    }
+
+    /// The list of all flags.
+    pub fn values() -> [Self; 6] {
+        [
+            Self::DryRun,
+            Self::File,
+            Self::Format,
+            Self::Once,
+            Self::Output,
+            Self::Strict,
+        ]
+    }
+}
+
+impl PrettyPrint for Flag {
+    fn pretty_print(&self) -> String {
+        match self {
+            Flag::DryRun => "--dry-run",
+            Flag::File => "-f",
+            Flag::Format => "--format",
+            Flag::Once => "--once",
+            Flag::Output => "--output",
+            Flag::Strict => "--strict",
+        }
+        .into()
+    }
+}
+
+/// An error that may occur when parsing the flags.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CliErr {
+    InvalidFormat(String),
+    InvalidOutput { path: String, kind: io::ErrorKind },
+    MissingFlagValue(Flag),
+    UnknownFlag(String),
+}
+
+impl PrettyPrint for CliErr {
+    fn pretty_print(&self) -> String {
+        match self {
+            CliErr::InvalidFormat(format) => format!(
+                "Invalid '{}' value '{}'; options are [{}].",
+                Flag::Format.pretty_print(),
+                format,
+                Format::values()
+                    .iter()
+                    .map(|f| f.pretty_print())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            CliErr::InvalidOutput { path, kind } => {
+                format!("Unable to write to '{}': {}", path, kind)
+            }
+            CliErr::MissingFlagValue(flag) => {
+                format!("Missing value for flag '{}'!", flag.pretty_print())
+            }
+            CliErr::UnknownFlag(flag) => format!(
+                "Unknown flag '{}'; options are [{}].",
+                flag,
+                Flag::values()
+                    .iter()
+                    .map(|f| f.pretty_print())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// The options for the program, sourced from the flags preceding the script.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CliOptions {
+    /// Whether to print what would be executed rather than executing it.
+    pub dry_run: bool,
+    /// The Witdfile to load commands from.
+    pub file: Option<String>,
+    /// The format events are reported in.
+    pub format: Format,
+    /// Whether to run every command once and exit, rather than watching.
+    pub once: bool,
+    /// The file to report events to rather than stdout.
+    pub output: Option<String>,
+    /// Whether file and traversal errors are fatal.
+    pub strict: bool,
+}
+
+impl CliOptions {
+    /// Parses the leading flags from the args, returning the options and the remaining args.
+    /// Only leading args are treated as flags so the script itself may contain dashes.
+    pub fn parse(args: &[String]) -> Result<(Self, Vec<String>), CliErr> {
+        let mut options = Self::default();
+        let mut args = args.iter().peekable();
+
+        while let Some(arg) = args.next_if(|a| a.starts_with('-')) {
+            match Flag::parse(arg) {
+                Some(Flag::DryRun) => options.dry_run = true,
+                Some(Flag::File) => {
+                    options.file = Some(
+                        args.next()
+                            .cloned()
+                            .ok_or(CliErr::MissingFlagValue(Flag::File))?,
+                    )
+                }
+                Some(Flag::Format) => {
+                    let format = args.next().ok_or(CliErr::MissingFlagValue(Flag::Format))?;
+                    options.format =
+                        Format::parse(format).ok_or(CliErr::InvalidFormat(format.clone()))?;
+                }
+                Some(Flag::Once) => options.once = true,
+                Some(Flag::Output) => {
+                    options.output = Some(
+                        args.next()
+                            .cloned()
+                            .ok_or(CliErr::MissingFlagValue(Flag::Output))?,
+                    )
+                }
+                Some(Flag::Strict) => options.strict = true,
+                None => return Err(CliErr::UnknownFlag(arg.clone())),
+            }
+        }
+
+        Ok((options, args.cloned().collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_ascii_whitespace().map(String::from).collect()
+    }
+
+    describe!(parse => {
+        #[test]
+        fn no_flags_returns_default(){
+            let expected = Ok((CliOptions::default(), args("directory . do echo end")));
+            assert_eq!(expected, CliOptions::parse(&args("directory . do echo end")));
+        }
+
+        #[test]
+        fn strict_sets_strict(){
+            let expected = Ok((CliOptions{ strict: true, ..Default::default() }, args("directory . do echo end")));
+            assert_eq!(expected, CliOptions::parse(&args("--strict directory . do echo end")));
+        }
+
+        #[test]
+        fn file_sets_file(){
+            let expected = Ok((CliOptions{ file: Some("Witdfile".into()), strict: true, ..Default::default() }, vec![]));
+            assert_eq!(expected, CliOptions::parse(&args("-f Witdfile --strict")));
+        }
+
+        #[test]
+        fn dry_run_sets_dry_run(){
+            let expected = Ok((CliOptions{ dry_run: true, ..Default::default() }, args("directory . do echo end")));
+            assert_eq!(expected, CliOptions::parse(&args("--dry-run directory . do echo end")));
+        }
+
+        #[test]
+        fn once_sets_once(){
+            let expected = Ok((CliOptions{ once: true, ..Default::default() }, args("directory . do echo end")));
+            assert_eq!(expected, CliOptions::parse(&args("--once directory . do echo end")));
+        }
+
+        #[test]
+        fn format_and_output_set_format_and_output(){
+            let expected = Ok((CliOptions{ format: Format::Json, output: Some("events.ndjson".into()), ..Default::default() }, args("directory . do echo end")));
+            assert_eq!(expected, CliOptions::parse(&args("--format json --output events.ndjson directory . do echo end")));
+        }
+
+        #[test]
+        fn invalid_format_returns_err(){
+            let expected = Err(CliErr::InvalidFormat("xml".into()));
+            assert_eq!(expected, CliOptions::parse(&args("--format xml directory . do echo end")));
+        }
+
+        #[test]
+        fn file_without_value_returns_err(){
+            let expected = Err(CliErr::MissingFlagValue(Flag::File));
+            assert_eq!(expected, CliOptions::parse(&args("-f")));
+        }
+
+        #[test]
+        fn flags_in_script_are_preserved(){
+            let expected = Ok((CliOptions::default(), args("directory . do rustfmt --check end")));
+            assert_eq!(expected, CliOptions::parse(&args("directory . do rustfmt --check end")));
+        }
+
+        #[test]
+        fn unknown_flag_returns_err(){
+            let expected = Err(CliErr::UnknownFlag("--garbage".into()));
+            assert_eq!(expected, CliOptions::parse(&args("--garbage directory . do echo end")));
+        }
+    });
+
+    describe!(pretty_print => {
+        #[test]
+        fn invalid_format(){
+            assert_eq!("Invalid '--format' value 'xml'; options are [json, text].", CliErr::InvalidFormat("xml".into()).pretty_print());
+        }
+
+        #[test]
+        fn invalid_output(){
+            let e = CliErr::InvalidOutput{ path: "out".into(), kind: io::ErrorKind::PermissionDenied };
+            assert_eq!(format!("Unable to write to 'out': {}", io::ErrorKind::PermissionDenied), e.pretty_print());
+        }
+
+        #[test]
+        fn dry_run(){
+            assert_eq!("--dry-run", Flag::DryRun.pretty_print());
+        }
+
+        #[test]
+        fn file(){
+            assert_eq!("-f", Flag::File.pretty_print());
+        }
+
+        #[test]
+        fn missing_flag_value(){
+            assert_eq!("Missing value for flag '-f'!", CliErr::MissingFlagValue(Flag::File).pretty_print());
+        }
+
+        #[test]
+        fn once(){
+            assert_eq!("--once", Flag::Once.pretty_print());
+        }
+
+        #[test]
+        fn strict(){
+            assert_eq!("--strict", Flag::Strict.pretty_print());
+        }
+
+        #[test]
+        fn unknown_flag(){
+            assert_eq!("Unknown flag '-x'; options are [--dry-run, -f, --format, --once, --output, --strict].", CliErr::UnknownFlag("-x".into()).pretty_print());
+        }
+    });
+}
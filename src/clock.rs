@@ -0,0 +1,79 @@
+use std::{
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// A job running in the background.
+pub trait Background: Send {
+    /// Returns whether the job has finished.
+    fn is_finished(&self) -> bool;
+
+    /// Waits for the job to finish, returning whether it succeeded.
+    fn join(self: Box<Self>) -> bool;
+}
+
+impl Background for JoinHandle<bool> {
+    fn is_finished(&self) -> bool {
+        JoinHandle::is_finished(self)
+    }
+
+    fn join(self: Box<Self>) -> bool {
+        // A panicking job counts as a failure; the panic has already been printed.
+        JoinHandle::join(*self).unwrap_or(false)
+    }
+}
+
+/// Tells the time and decides when background jobs finish, so the runner can be driven by a
+/// virtual clock rather than real time.
+pub trait Clock: Send + Sync {
+    /// Returns the time elapsed since the clock started.
+    fn now(&self) -> Duration;
+
+    /// Starts running the job in the background.
+    fn spawn(&self, job: Box<dyn FnOnce() -> bool + Send>) -> Box<dyn Background>;
+}
+
+/// Tells the real time and runs each job on its own thread.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SystemClock {
+    started: Instant,
+}
+
+impl SystemClock {
+    /// Creates a new clock starting now.
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    fn spawn(&self, job: Box<dyn FnOnce() -> bool + Send>) -> Box<dyn Background> {
+        Box::new(thread::spawn(job))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    describe!(spawn => {
+        #[test]
+        fn system_returns_result(){
+            let clock = SystemClock::new();
+            assert!(clock.spawn(Box::new(|| true)).join());
+            assert!(!clock.spawn(Box::new(|| false)).join());
+        }
+    });
+}
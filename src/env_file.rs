@@ -0,0 +1,193 @@
+use crate::traits::PrettyPrint;
+use std::{env, fs, io};
+
+/// The name of the env file that is discovered next to a Witdfile.
+pub const DEFAULT_PATH: &str = ".env";
+
+/// An error that may occur when loading an env file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EnvFileErr {
+    InvalidLine { path: String, line: usize },
+    Read { path: String, kind: io::ErrorKind },
+    UnterminatedQuote { path: String, line: usize },
+}
+
+impl PrettyPrint for EnvFileErr {
+    fn pretty_print(&self) -> String {
+        match self {
+            EnvFileErr::InvalidLine { path, line } => {
+                format!("{}:{}: Expected 'KEY=VALUE'!", path, line)
+            }
+            EnvFileErr::Read { path, kind } => format!("Unable to read '{}': {}", path, kind),
+            EnvFileErr::UnterminatedQuote { path, line } => {
+                format!("{}:{}: Missing closing quote!", path, line)
+            }
+        }
+    }
+}
+
+/// Loads the variables from the env file at the given path.
+pub fn load(path: &str) -> Result<Vec<(String, String)>, EnvFileErr> {
+    let contents = fs::read_to_string(path).map_err(|e| EnvFileErr::Read {
+        path: path.to_string(),
+        kind: e.kind(),
+    })?;
+
+    parse(path, &contents)
+}
+
+/// Parses the variables from the contents of an env file, using dotenv syntax.
+/// `${VAR}` expands to an earlier variable in the file, falling back to the process environment.
+pub fn parse(path: &str, contents: &str) -> Result<Vec<(String, String)>, EnvFileErr> {
+    let mut variables: Vec<(String, String)> = vec![];
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => (key.trim(), value.trim()),
+            _ => {
+                return Err(EnvFileErr::InvalidLine {
+                    path: path.to_string(),
+                    line: i + 1,
+                })
+            }
+        };
+
+        let value = parse_value(value, &variables).ok_or(EnvFileErr::UnterminatedQuote {
+            path: path.to_string(),
+            line: i + 1,
+        })?;
+
+        variables.retain(|(k, _)| k != key);
+        variables.push((key.to_string(), value));
+    }
+
+    Ok(variables)
+}
+
+/// Expands each `${VAR}` in the value.
+fn expand(value: &str, variables: &[(String, String)]) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+
+        let name = &rest[start + 2..start + end];
+        expanded.push_str(&rest[..start]);
+        match variables.iter().rev().find(|(k, _)| k == name) {
+            Some((_, value)) => expanded.push_str(value),
+            None => expanded.push_str(&env::var(name).unwrap_or_default()),
+        }
+        rest = &rest[start + end + 1..];
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Parses a single value. Single quoted values are literal, double quoted values support escapes
+/// and expansion, and unquoted values end at a comment. Returns none if a quote is not closed.
+fn parse_value(value: &str, variables: &[(String, String)]) -> Option<String> {
+    if let Some(value) = value.strip_prefix('\'') {
+        let end = value.find('\'')?;
+        return Some(value[..end].to_string());
+    }
+
+    if let Some(value) = value.strip_prefix('"') {
+        let mut unescaped = String::new();
+        let mut chars = value.chars();
+        loop {
+            match chars.next()? {
+                '"' => return Some(expand(&unescaped, variables)),
+                '\\' => match chars.next()? {
+                    'n' => unescaped.push('\n'),
+                    't' => unescaped.push('\t'),
+                    c => unescaped.push(c),
+                },
+                c => unescaped.push(c),
+            }
+        }
+    }
+
+    let value = match value.find(" #") {
+        Some(i) => &value[..i],
+        None => value,
+    };
+    Some(expand(value.trim(), variables))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    describe!(parse => {
+        #[test]
+        fn parses_variables(){
+            let contents = "# Database\nHOST=localhost\n\nexport PORT = 5432 # default\n";
+
+            let expected = Ok(vars(&[("HOST", "localhost"), ("PORT", "5432")]));
+            assert_eq!(expected, parse(".env", contents));
+        }
+
+        #[test]
+        fn parses_quoted_values(){
+            let contents = "A=\"hello # world\\n\"\nB='${A} stays'\nC=\"say \\\"hi\\\"\"";
+
+            let expected = Ok(vars(&[("A", "hello # world\n"), ("B", "${A} stays"), ("C", "say \"hi\"")]));
+            assert_eq!(expected, parse(".env", contents));
+        }
+
+        #[test]
+        fn expands_variables(){
+            let contents = "HOST=localhost\nURL=\"http://${HOST}:${WITD_UNSET_FOR_TEST}/\"\nHOST=example.com\nOTHER=${HOST}";
+
+            let expected = Ok(vars(&[("URL", "http://localhost:/"), ("HOST", "example.com"), ("OTHER", "example.com")]));
+            assert_eq!(expected, parse(".env", contents));
+        }
+
+        #[test]
+        fn invalid_line_returns_err(){
+            let expected = Err(EnvFileErr::InvalidLine{ path: ".env".into(), line: 2 });
+            assert_eq!(expected, parse(".env", "A=1\nB\n"));
+        }
+
+        #[test]
+        fn unterminated_quote_returns_err(){
+            let expected = Err(EnvFileErr::UnterminatedQuote{ path: ".env".into(), line: 1 });
+            assert_eq!(expected, parse(".env", "A=\"oops"));
+        }
+    });
+
+    describe!(pretty_print => {
+        #[test]
+        fn invalid_line(){
+            assert_eq!(".env:2: Expected 'KEY=VALUE'!", EnvFileErr::InvalidLine{ path: ".env".into(), line: 2 }.pretty_print());
+        }
+
+        #[test]
+        fn read(){
+            let e = EnvFileErr::Read{ path: ".env".into(), kind: io::ErrorKind::NotFound };
+            assert_eq!(format!("Unable to read '.env': {}", io::ErrorKind::NotFound), e.pretty_print());
+        }
+
+        #[test]
+        fn unterminated_quote(){
+            assert_eq!(".env:1: Missing closing quote!", EnvFileErr::UnterminatedQuote{ path: ".env".into(), line: 1 }.pretty_print());
+        }
+    });
+}
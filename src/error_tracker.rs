@@ -0,0 +1,96 @@
+use crate::{list_all_files::FileErr, traits::PrettyPrint};
+use std::collections::BTreeMap;
+
+/// Tracks the file errors seen on each scan so they are only reported when their state changes.
+#[derive(Debug, Default, PartialEq)]
+pub struct ErrorTracker {
+    errors: BTreeMap<String, FileErr>,
+}
+
+impl ErrorTracker {
+    /// Creates a new error tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the tracker with the errors from the latest scan, returning the messages to report.
+    /// New or changed errors are reported, as are paths whose errors have been resolved.
+    pub fn update(&mut self, errors: Vec<FileErr>) -> Vec<String> {
+        let mut messages = vec![];
+        let mut current = BTreeMap::new();
+
+        for error in errors {
+            if self.errors.get(error.path()) != Some(&error) {
+                messages.push(error.pretty_print());
+            }
+
+            current.insert(error.path().clone(), error);
+        }
+
+        for path in self.errors.keys() {
+            if !current.contains_key(path) {
+                messages.push(format!("'{}' is no longer failing.", path));
+            }
+        }
+
+        self.errors = current;
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    fn err(path: &str, kind: ErrorKind) -> FileErr {
+        FileErr::Traversal {
+            path: path.into(),
+            kind,
+        }
+    }
+
+    describe!(update => {
+        #[test]
+        fn no_errors_returns_empty(){
+            let mut tracker = ErrorTracker::new();
+            assert!(tracker.update(vec![]).is_empty());
+        }
+
+        #[test]
+        fn new_error_is_reported(){
+            let mut tracker = ErrorTracker::new();
+            let e = err("a", ErrorKind::PermissionDenied);
+
+            assert_eq!(vec![e.pretty_print()], tracker.update(vec![e]));
+        }
+
+        #[test]
+        fn repeated_error_is_not_reported(){
+            let mut tracker = ErrorTracker::new();
+            let e = err("a", ErrorKind::PermissionDenied);
+
+            tracker.update(vec![e.clone()]);
+
+            assert!(tracker.update(vec![e]).is_empty());
+        }
+
+        #[test]
+        fn changed_error_is_reported(){
+            let mut tracker = ErrorTracker::new();
+            tracker.update(vec![err("a", ErrorKind::PermissionDenied)]);
+
+            let e = err("a", ErrorKind::NotFound);
+            assert_eq!(vec![e.pretty_print()], tracker.update(vec![e]));
+        }
+
+        #[test]
+        fn resolved_error_is_reported_once(){
+            let mut tracker = ErrorTracker::new();
+            tracker.update(vec![err("a", ErrorKind::PermissionDenied)]);
+
+            assert_eq!(vec!["'a' is no longer failing.".to_string()], tracker.update(vec![]));
+            assert!(tracker.update(vec![]).is_empty());
+        }
+    });
+}
@@ -0,0 +1,34 @@
+//! Watches directories and runs commands whenever the files in them change.
+//!
+//! Commands are parsed from their syntax with `Command::parse`, loaded from a Witdfile with
+//! `witdfile::load`, or built with `Command::builder`. A `Runner` then watches them, reporting
+//! everything that happens as an `Event` to a `Reporter` or callback.
+
+#[cfg(test)]
+#[macro_use]
+mod test_helpers;
+
+mod clock;
+mod env_file;
+mod error_tracker;
+mod list_all_files;
+mod runner;
+#[cfg(test)]
+mod simulation;
+mod state;
+mod summary;
+mod traits;
+mod types;
+mod witd;
+pub mod witdfile;
+
+pub use crate::{
+    runner::Runner,
+    summary::{Summary, SummaryRow, TaskResult},
+    traits::PrettyPrint,
+    types::File,
+    witd::{
+        Change, Command, CommandBuilder, CommandErr, Event, Format, Outcome, Reason, Reporter,
+        RunMode, ScriptOptions, StartupMode, StdinFormat, Stream, StreamReporter, TaskErr, WitdErr,
+    },
+};
@@ -1,52 +1,292 @@
-use crate::types::File;
-use std::time::SystemTime;
-use walkdir::WalkDir;
-
-/// Retrieves all files under the given path.
-pub fn execute(path: &str) -> Vec<File> {
-    WalkDir::new(path)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(|e| !e.file_type().is_dir())
-        .map(|f| {
-            let name = String::from(f.file_name().to_string_lossy());
-            let extension = f
-                .path()
-                .extension()
-                .unwrap_or_default()
-                .to_str()
-                .unwrap_or_default()
-                .to_string();
-            let path = f.path().clone();
-            let directory = match f.path().parent() {
-                Some(p) => p.to_string_lossy().to_string(),
-                None => "".into(),
-            };
+use crate::{
+    traits::PrettyPrint,
+    types::{File, WalkOptions},
+};
+use std::{fs, io, time::SystemTime};
+use walkdir::{DirEntry, WalkDir};
 
-            let modified_at = f
-                .metadata()
-                .unwrap()
-                .modified()
-                .unwrap()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap();
+/// An error that may occur while reading a single file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FileErr {
+    Loop { path: String, ancestor: String },
+    Metadata { path: String, kind: io::ErrorKind },
+    ModifiedAt { path: String, kind: io::ErrorKind },
+    Traversal { path: String, kind: io::ErrorKind },
+}
 
-            let created_at = f
-                .metadata()
-                .unwrap()
-                .created()
-                .unwrap()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap();
+impl FileErr {
+    /// Returns the path the error occurred on.
+    pub fn path(&self) -> &String {
+        match self {
+            FileErr::Loop { path, .. } => path,
+            FileErr::Metadata { path, .. } => path,
+            FileErr::ModifiedAt { path, .. } => path,
+            FileErr::Traversal { path, .. } => path,
+        }
+    }
+}
 
-            File {
-                created_at,
-                directory,
-                name,
-                path: path.to_string_lossy().to_string(),
-                extension,
-                modified_at,
+impl PrettyPrint for FileErr {
+    fn pretty_print(&self) -> String {
+        match self {
+            FileErr::Loop { path, ancestor } => {
+                format!("Symlink loop at '{}' back to '{}'", path, ancestor)
+            }
+            FileErr::Metadata { path, kind } => {
+                format!("Unable to read metadata for '{}': {}", path, kind)
+            }
+            FileErr::ModifiedAt { path, kind } => {
+                format!("Unable to read modified time for '{}': {}", path, kind)
+            }
+            FileErr::Traversal { path, kind } => {
+                format!("Unable to traverse '{}': {}", path, kind)
+            }
+        }
+    }
+}
+
+/// Lists the files under a path, so the runner can watch a virtual file tree rather than the disk.
+pub trait FileSource: Send + Sync {
+    /// Lists every file under the path. Errors for individual files are returned in place.
+    fn list(&self, path: &str, options: &WalkOptions) -> Vec<Result<File, FileErr>>;
+}
+
+/// Lists the files on disk.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Disk;
+
+impl FileSource for Disk {
+    fn list(&self, path: &str, options: &WalkOptions) -> Vec<Result<File, FileErr>> {
+        execute(path, options)
+    }
+}
+
+/// Retrieves all files under the given path. Errors for individual files are returned in place so the rest of the walk continues.
+pub fn execute(path: &str, options: &WalkOptions) -> Vec<Result<File, FileErr>> {
+    let mut walk = WalkDir::new(path).follow_links(options.follow_links);
+    if let Some(max_depth) = options.max_depth {
+        walk = walk.max_depth(max_depth);
+    }
+    if let Some(min_depth) = options.min_depth {
+        walk = walk.min_depth(min_depth);
+    }
+
+    walk.into_iter()
+        // Prune hidden directories here so they are never descended into. The root is always kept.
+        .filter_entry(|e| !options.skip_hidden || e.depth() == 0 || !is_hidden(e))
+        .filter(|e| match e {
+            Ok(e) => !e.file_type().is_dir(),
+            Err(_) => true,
+        })
+        .map(|e| match e {
+            Ok(e) => build_file(&e, path, options),
+            Err(e) => {
+                let err_path = e
+                    .path()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.to_string());
+
+                match e.loop_ancestor() {
+                    Some(ancestor) => Err(FileErr::Loop {
+                        path: err_path,
+                        ancestor: ancestor.to_string_lossy().to_string(),
+                    }),
+                    None => Err(FileErr::Traversal {
+                        path: err_path,
+                        kind: e
+                            .io_error()
+                            .map(io::Error::kind)
+                            .unwrap_or(io::ErrorKind::Other),
+                    }),
+                }
             }
         })
         .collect()
 }
+
+/// Returns whether the entry is a dotfile or dot-directory.
+fn is_hidden(e: &DirEntry) -> bool {
+    e.file_name().to_string_lossy().starts_with('.')
+}
+
+/// Builds a file from the given entry.
+fn build_file(f: &DirEntry, root: &str, options: &WalkOptions) -> Result<File, FileErr> {
+    let name = String::from(f.file_name().to_string_lossy());
+    let extension = f
+        .path()
+        .extension()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap_or_default()
+        .to_string();
+    let path = f.path().to_string_lossy().to_string();
+    let directory = match f.path().parent() {
+        Some(p) => p.to_string_lossy().to_string(),
+        None => "".into(),
+    };
+
+    // Only resolve when following links; otherwise the path is never redirected.
+    let target = if options.follow_links {
+        fs::canonicalize(f.path())
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| path.clone())
+    } else {
+        path.clone()
+    };
+
+    let metadata = f.metadata().map_err(|e| FileErr::Metadata {
+        path: path.clone(),
+        kind: e
+            .io_error()
+            .map(io::Error::kind)
+            .unwrap_or(io::ErrorKind::Other),
+    })?;
+
+    let modified_at = metadata
+        .modified()
+        .map_err(|e| FileErr::ModifiedAt {
+            path: path.clone(),
+            kind: e.kind(),
+        })?
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    // Birth time is not supported on all filesystems (tmpfs, some overlayfs, NFS), so it is optional.
+    let created_at = metadata
+        .created()
+        .ok()
+        .map(|t| t.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default());
+
+    Ok(File {
+        created_at,
+        directory,
+        name,
+        path,
+        extension,
+        modified_at,
+        root: root.to_string(),
+        target,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    describe!(execute => {
+        #[test]
+        fn lists_files(){
+            let files = execute("src", &WalkOptions::default());
+            let main = files
+                .iter()
+                .filter_map(|f| f.as_ref().ok())
+                .find(|f| f.name == "main.rs")
+                .unwrap();
+
+            assert_eq!("src", main.directory);
+            assert_eq!("rs", main.extension);
+            assert_eq!("src", main.root);
+        }
+
+        #[test]
+        fn nonexistant_path_returns_traversal_err(){
+            let expected = vec![Err(FileErr::Traversal{ path: "./does/not/exist".into(), kind: io::ErrorKind::NotFound })];
+            assert_eq!(expected, execute("./does/not/exist", &WalkOptions::default()));
+        }
+
+        #[test]
+        fn depth_limits_and_hidden_files_are_applied(){
+            let root = std::env::temp_dir().join(format!("witd-depth-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(root.join("a/b")).unwrap();
+            fs::create_dir_all(root.join(".git")).unwrap();
+            fs::write(root.join("top.txt"), "").unwrap();
+            fs::write(root.join(".hidden"), "").unwrap();
+            fs::write(root.join("a/mid.txt"), "").unwrap();
+            fs::write(root.join("a/b/deep.txt"), "").unwrap();
+            fs::write(root.join(".git/HEAD"), "").unwrap();
+
+            let names = |options: WalkOptions| {
+                let mut names: Vec<String> = execute(&root.to_string_lossy(), &options)
+                    .into_iter()
+                    .map(|f| f.unwrap().name)
+                    .collect();
+                names.sort();
+                names
+            };
+
+            let all = names(WalkOptions::default());
+            let max = names(WalkOptions{ max_depth: Some(2), ..Default::default() });
+            let min = names(WalkOptions{ min_depth: Some(2), ..Default::default() });
+            let visible = names(WalkOptions{ skip_hidden: true, ..Default::default() });
+            let _ = fs::remove_dir_all(&root);
+
+            assert_eq!(vec![".hidden", "HEAD", "deep.txt", "mid.txt", "top.txt"], all);
+            assert_eq!(vec![".hidden", "HEAD", "mid.txt", "top.txt"], max);
+            assert_eq!(vec!["HEAD", "deep.txt", "mid.txt"], min);
+            assert_eq!(vec!["deep.txt", "mid.txt", "top.txt"], visible);
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn follow_links_resolves_targets_and_reports_loops(){
+            let root = std::env::temp_dir().join(format!("witd-follow-links-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(root.join("real")).unwrap();
+            fs::write(root.join("real/a.txt"), "a").unwrap();
+            std::os::unix::fs::symlink(root.join("real"), root.join("link")).unwrap();
+            std::os::unix::fs::symlink(&root, root.join("real/loop")).unwrap();
+
+            let results = execute(&root.to_string_lossy(), &WalkOptions{ follow_links: true, ..Default::default() });
+            let _ = fs::remove_dir_all(&root);
+
+            let linked = results
+                .iter()
+                .filter_map(|f| f.as_ref().ok())
+                .find(|f| f.path.ends_with("link/a.txt"))
+                .unwrap();
+            let canonical_root = fs::canonicalize(std::env::temp_dir()).unwrap();
+            assert!(linked.target.starts_with(&*canonical_root.to_string_lossy()));
+            assert!(linked.target.ends_with("real/a.txt"));
+
+            assert!(results.iter().any(|r| matches!(r, Err(FileErr::Loop{ .. }))));
+        }
+    });
+
+    describe!(path => {
+        #[test]
+        fn returns_path(){
+            let kind = io::ErrorKind::NotFound;
+            assert_eq!("l", FileErr::Loop{ path: "l".into(), ancestor: "a".into() }.path());
+            assert_eq!("a", FileErr::Metadata{ path: "a".into(), kind }.path());
+            assert_eq!("b", FileErr::ModifiedAt{ path: "b".into(), kind }.path());
+            assert_eq!("c", FileErr::Traversal{ path: "c".into(), kind }.path());
+        }
+    });
+
+    describe!(pretty_print => {
+        #[test]
+        fn loop_(){
+            let e = FileErr::Loop{ path: "a/b/loop".into(), ancestor: "a".into() };
+            assert_eq!("Symlink loop at 'a/b/loop' back to 'a'", e.pretty_print());
+        }
+
+        #[test]
+        fn metadata(){
+            let e = FileErr::Metadata{ path: "a.txt".into(), kind: io::ErrorKind::NotFound };
+            assert_eq!(format!("Unable to read metadata for 'a.txt': {}", io::ErrorKind::NotFound), e.pretty_print());
+        }
+
+        #[test]
+        fn modified_at(){
+            let e = FileErr::ModifiedAt{ path: "a.txt".into(), kind: io::ErrorKind::Unsupported };
+            assert_eq!(format!("Unable to read modified time for 'a.txt': {}", io::ErrorKind::Unsupported), e.pretty_print());
+        }
+
+        #[test]
+        fn traversal(){
+            let e = FileErr::Traversal{ path: "secret".into(), kind: io::ErrorKind::PermissionDenied };
+            assert_eq!(format!("Unable to traverse 'secret': {}", io::ErrorKind::PermissionDenied), e.pretty_print());
+        }
+    });
+}
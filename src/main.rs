@@ -1,69 +1,132 @@
 #[cfg(test)]
 #[macro_use]
-mod test_helpers;
+#[path = "test_helpers/describe.rs"]
+mod describe;
 
-mod list_all_files;
-mod traits;
-mod types;
-mod witd;
+mod cli_options;
 
-use crate::{
-    traits::PrettyPrint,
-    witd::{Command, CommandErr, Witd, WitdErr},
+use cli_options::{CliErr, CliOptions};
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::Path,
+    process,
+    sync::Arc,
 };
-use std::env;
+use witd::{
+    witdfile, witdfile::WitdfileErr, Command, CommandErr, Event, PrettyPrint, Reporter, Runner,
+    StreamReporter, TaskErr, WitdErr,
+};
+
+/// Source the options and input from the env args
+fn get_cli_input() -> Result<(CliOptions, String), CliErr> {
+    let args: Vec<String> = env::args().skip(1).collect();
 
-/// A simple structure containing information related to each Witd instance.
-struct Context {
-    path: String,
-    witd: Witd,
+    match CliOptions::parse(&args) {
+        Ok((options, args)) => Ok((options, args.join(" "))),
+        Err(e) => {
+            eprintln!("Error: {}", e.pretty_print());
+            Err(e)
+        }
+    }
 }
 
-/// Builds out a collection of Contexts from a collection of inputs.
-fn build_contexts(inputs: Vec<String>) -> Result<Vec<Context>, WitdErr> {
-    let mut contexts = vec![];
+/// Attempts to parse the command from a string.
+fn handle_parse_command(command: &str, reporter: &dyn Reporter) -> Result<Command, CommandErr> {
+    match Command::parse(command) {
+        Ok(command) => Ok(command),
+        Err(e) => {
+            let examples = Command::examples()
+                .iter()
+                .map(|m| format!("Example: {}", m))
+                .collect::<Vec<String>>()
+                .join("\n");
+            report_error(reporter, format!("{}\n{}", e.pretty_print(), examples));
+            Err(e)
+        }
+    }
+}
 
-    for input in inputs {
-        let command = handle_parse_command(&input)?;
-        contexts.push(Context {
-            path: command.root_path().clone(),
-            witd: Witd::new(command),
-        });
+/// Attempts to load the commands from a Witdfile.
+fn handle_load_witdfile(path: &str, reporter: &dyn Reporter) -> Result<Vec<Command>, WitdfileErr> {
+    match witdfile::load(path) {
+        Ok(commands) => Ok(commands),
+        Err(e) => {
+            report_error(reporter, e.pretty_print());
+            Err(e)
+        }
     }
+}
 
-    Ok(contexts)
+/// Loads the commands from the Witdfile and the CLI input.
+/// If neither a Witdfile nor any input is provided, `./Witdfile` is loaded if it exists.
+fn load_commands(
+    options: &CliOptions,
+    input: String,
+    reporter: &dyn Reporter,
+) -> Result<Vec<Command>, WitdErr> {
+    let mut commands = vec![];
+
+    let path = match &options.file {
+        Some(path) => Some(path.as_str()),
+        None if input.trim().is_empty() && Path::new(witdfile::DEFAULT_PATH).is_file() => {
+            Some(witdfile::DEFAULT_PATH)
+        }
+        None => None,
+    };
+
+    if let Some(path) = path {
+        commands.extend(handle_load_witdfile(path, reporter)?);
+    }
+
+    if !input.trim().is_empty() || path.is_none() {
+        for input in split_commands(input) {
+            commands.push(handle_parse_command(&input, reporter)?);
+        }
+    }
+
+    Ok(commands)
 }
 
-/// Source the input from the env args
-fn get_cli_input() -> String {
-    let args: Vec<String> = env::args().collect();
+/// Creates the reporter for the given options, writing to the output file if one was given.
+fn handle_open_reporter(options: &CliOptions) -> Result<Arc<dyn Reporter>, CliErr> {
+    let stream: Box<dyn Write + Send> = match &options.output {
+        Some(path) => match fs::File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(e) => {
+                let e = CliErr::InvalidOutput {
+                    path: path.clone(),
+                    kind: e.kind(),
+                };
+                eprintln!("Error: {}", e.pretty_print());
+                return Err(e);
+            }
+        },
+        None => Box::new(io::stdout()),
+    };
 
-    args.iter()
-        .skip(1)
-        .map(|s| s.clone())
-        .collect::<Vec<String>>()
-        .join(" ")
+    Ok(Arc::new(StreamReporter::new(options.format, stream)))
 }
 
-/// Attempts to parse the command from a string.
-fn handle_parse_command(command: &str) -> Result<Command, CommandErr> {
-    match Command::parse(command) {
-        Ok(command) => Ok(command),
+/// Attempts to create the runner, sorting the commands by the tasks they depend on.
+fn handle_new_runner(commands: Vec<Command>, reporter: &dyn Reporter) -> Result<Runner, TaskErr> {
+    match Runner::new(commands) {
+        Ok(runner) => Ok(runner),
         Err(e) => {
-            println!("Error: {}", e.pretty_print());
-            println!(
-                "{}",
-                Command::examples()
-                    .iter()
-                    .map(|m| format!("Example: {}", m))
-                    .collect::<Vec<String>>()
-                    .join("\n")
-            );
-            Err(e.into())
+            report_error(reporter, e.pretty_print());
+            Err(e)
         }
     }
 }
 
+/// Reports an error that is not specific to any task.
+fn report_error(reporter: &dyn Reporter, message: String) {
+    reporter.report(Event::Error {
+        task: None,
+        message,
+    });
+}
+
 /// Given some input, split it into a collection of different inputs.
 fn split_commands(input: String) -> Vec<String> {
     if input.contains(";;") {
@@ -76,17 +139,45 @@ fn split_commands(input: String) -> Vec<String> {
     }
 }
 
-fn main() -> Result<(), WitdErr> {
-    // Source all data
-    let input = get_cli_input();
-    let inputs = split_commands(input);
-    let mut contexts = build_contexts(inputs)?;
-
-    // Now constantly loop, executing the files and commands
-    loop {
-        for context in contexts.iter_mut() {
-            let files = list_all_files::execute(&context.path);
-            context.witd.execute(files)?;
+fn main() {
+    // Errors are printed to stderr until the format is known, then reported in that format.
+    let Ok((options, input)) = get_cli_input() else {
+        process::exit(1);
+    };
+    let Ok(reporter) = handle_open_reporter(&options) else {
+        process::exit(1);
+    };
+    let Ok(commands) = load_commands(&options, input, reporter.as_ref()) else {
+        process::exit(1);
+    };
+
+    let Ok(runner) = handle_new_runner(commands, reporter.as_ref()) else {
+        process::exit(1);
+    };
+    let mut runner = runner.with_reporter(reporter.clone()).with_default_state();
+    if let Some(output) = &options.output {
+        runner = runner.with_ignored(output);
+    }
+    if options.dry_run {
+        runner = runner.with_dry_run();
+    }
+    if options.strict {
+        runner = runner.with_strict();
+    }
+
+    // Fatal runner errors have already been reported.
+    if options.once {
+        match runner.run_once() {
+            Ok(summary) => {
+                let passed = summary.passed();
+                reporter.report(Event::Summary(summary));
+                if !passed {
+                    process::exit(1);
+                }
+            }
+            Err(_) => process::exit(1),
         }
+    } else if runner.run().is_err() {
+        process::exit(1);
     }
 }
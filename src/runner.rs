@@ -0,0 +1,601 @@
+use crate::{
+    clock::{Background, Clock, SystemClock},
+    error_tracker::ErrorTracker,
+    list_all_files::{Disk, FileSource},
+    state::{self, State},
+    summary::{Summary, SummaryRow, TaskResult},
+    traits::PrettyPrint,
+    types::{File, WalkOptions},
+    witd::{
+        sort_tasks, Command, Event, Executor, Format, Job, ProcessExecutor, Reason, Reporter,
+        RunQueue, StreamReporter, TaskErr, Witd, WitdErr,
+    },
+};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// A simple structure containing information related to each Witd instance.
+struct Context {
+    dependencies: Vec<usize>,
+    errors: ErrorTracker,
+    held: Option<Job>,
+    key: String,
+    paths: Vec<String>,
+    queue: RunQueue,
+    running: Option<(Job, Box<dyn Background>)>,
+    scanned: Option<(usize, usize)>,
+    succeeded: Option<bool>,
+    walk_options: WalkOptions,
+    witd: Witd<Arc<dyn Executor>>,
+}
+
+impl Context {
+    /// Holds the given job until the tasks this context depends on are ready.
+    fn hold(&mut self, job: Job) {
+        match &mut self.held {
+            Some(held) => held.merge(job),
+            None => self.held = Some(job),
+        }
+    }
+
+    /// Returns whether tasks depending on this context may run; it must be idle and not have failed.
+    fn is_ready_upstream(&self) -> bool {
+        self.queue == RunQueue::Idle && self.succeeded != Some(false)
+    }
+
+    /// Checks whether the running job has finished, starting the follow-up job if one is pending.
+    /// The files of a successful job are finished with. Returns whether the job succeeded if it
+    /// finished.
+    fn poll(&mut self, clock: &dyn Clock, reporter: &Arc<dyn Reporter>) -> Option<bool> {
+        if !self.running.as_ref().is_some_and(|(_, r)| r.is_finished()) {
+            return None;
+        }
+
+        let (job, running) = self.running.take()?;
+        let succeeded = running.join();
+        if succeeded {
+            self.witd.complete(&job);
+        }
+        self.succeeded = Some(succeeded);
+
+        if let Some(job) = self.queue.finish() {
+            self.start(job, clock, reporter);
+        }
+
+        Some(succeeded)
+    }
+
+    /// Queues the held job, starting it if nothing is running.
+    fn release(&mut self, clock: &dyn Clock, reporter: &Arc<dyn Reporter>) {
+        if let Some(job) = self.held.take().and_then(|job| self.queue.push(job)) {
+            self.start(job, clock, reporter);
+        }
+    }
+
+    /// Lists every file under the context's paths that the command's `where` clauses match, other
+    /// than witd's own state and the ignored paths. File errors are fatal if strict, otherwise they
+    /// are reported once per path so the remaining files are still watched. The scan is reported
+    /// whenever the number of files or errors changes.
+    fn scan(
+        &mut self,
+        file_source: &dyn FileSource,
+        strict: bool,
+        ignored: &[PathBuf],
+        reporter: &dyn Reporter,
+    ) -> Result<Vec<File>, WitdErr> {
+        let mut files = vec![];
+        let mut errors = vec![];
+        for path in self.paths.iter() {
+            for result in file_source.list(path, &self.walk_options) {
+                match result {
+                    Ok(file) if state::is_state_path(&file.path) => {}
+                    Ok(file) if is_ignored(&file.path, ignored) => {}
+                    Ok(file) if !self.witd.command().matches(&file) => {}
+                    Ok(file) => files.push(file),
+                    Err(e) => errors.push(e),
+                }
+            }
+        }
+
+        if self.scanned != Some((files.len(), errors.len())) {
+            self.scanned = Some((files.len(), errors.len()));
+            reporter.report(Event::Scan {
+                task: self.witd.command().label(),
+                files: files.len(),
+                errors: errors.len(),
+            });
+        }
+
+        if strict {
+            if let Some(e) = errors.into_iter().next() {
+                reporter.report(Event::Error {
+                    task: None,
+                    message: e.pretty_print(),
+                });
+                return Err(e.into());
+            }
+        } else {
+            for message in self.errors.update(errors) {
+                reporter.report(Event::Warning { message });
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Starts the given job in the background so scanning continues while it runs.
+    fn start(&mut self, job: Job, clock: &dyn Clock, reporter: &Arc<dyn Reporter>) {
+        let command = self.witd.command().clone();
+        let executor = self.witd.executor().clone();
+        let reporter = reporter.clone();
+        let running = {
+            let job = job.clone();
+            clock.spawn(Box::new(move || {
+                command.run(&job, executor.as_ref(), reporter.as_ref())
+            }))
+        };
+        self.running = Some((job, running));
+    }
+}
+
+/// Watches the files of every command, running each command whenever its files change.
+/// Commands run after the tasks they depend on, and everything that happens is reported as events.
+pub struct Runner {
+    clock: Arc<dyn Clock>,
+    contexts: Vec<Context>,
+    dry_run: bool,
+    file_source: Arc<dyn FileSource>,
+    ignored: Vec<PathBuf>,
+    is_restored: bool,
+    reporter: Arc<dyn Reporter>,
+    state: State,
+    state_path: Option<String>,
+    strict: bool,
+}
+
+impl Runner {
+    /// Creates a new runner for the given commands, running them as child processes and
+    /// reporting events as text to stdout.
+    pub fn new(commands: Vec<Command>) -> Result<Self, TaskErr> {
+        let executor: Arc<dyn Executor> = Arc::new(ProcessExecutor);
+        let mut contexts = vec![];
+        for task in sort_tasks(commands)? {
+            let command = task.command;
+            contexts.push(Context {
+                dependencies: task.dependencies,
+                errors: ErrorTracker::new(),
+                held: None,
+                key: command.key(),
+                paths: command.root_paths().to_vec(),
+                queue: RunQueue::default(),
+                running: None,
+                scanned: None,
+                succeeded: None,
+                walk_options: command.walk_options().clone(),
+                witd: Witd::new(command).with_executor(executor.clone()),
+            });
+        }
+
+        Ok(Self {
+            clock: Arc::new(SystemClock::new()),
+            contexts,
+            dry_run: false,
+            file_source: Arc::new(Disk),
+            ignored: vec![],
+            is_restored: false,
+            reporter: Arc::new(StreamReporter::new(Format::Text, Box::new(io::stdout()))),
+            state: State::default(),
+            state_path: None,
+            strict: false,
+        })
+    }
+
+    /// Runs forever, watching for changes. Only returns if an error is fatal.
+    pub fn run(&mut self) -> Result<(), WitdErr> {
+        loop {
+            self.tick()?;
+        }
+    }
+
+    /// Runs every command once over all of its files, in dependency order, returning the results.
+    /// A task is skipped if any task it depends on did not pass.
+    pub fn run_once(&mut self) -> Result<Summary, WitdErr> {
+        let mut summary = Summary::default();
+
+        for context in self.contexts.iter_mut() {
+            let task = context.witd.command().label();
+            let is_ready = context
+                .dependencies
+                .iter()
+                .all(|d| summary.rows[*d].result == TaskResult::Passed);
+
+            if !is_ready {
+                summary.rows.push(SummaryRow {
+                    duration: None,
+                    result: TaskResult::Skipped,
+                    task,
+                });
+                continue;
+            }
+
+            let job = Job {
+                changes: BTreeMap::new(),
+                files: context.scan(
+                    self.file_source.as_ref(),
+                    self.strict,
+                    &self.ignored,
+                    self.reporter.as_ref(),
+                )?,
+                run_mode: context.witd.command().run_mode(),
+            };
+
+            self.reporter.report(Event::Triggered {
+                task: task.clone(),
+                path: None,
+                change: None,
+                reason: Reason::Once,
+            });
+
+            let started = self.clock.now();
+            let succeeded = context.witd.execute(&job, self.reporter.as_ref());
+            summary.rows.push(SummaryRow {
+                duration: Some(self.clock.now().saturating_sub(started)),
+                result: if succeeded {
+                    TaskResult::Passed
+                } else {
+                    TaskResult::Failed
+                },
+                task,
+            });
+        }
+
+        Ok(summary)
+    }
+
+    /// Returns the files seen so far, as they would be persisted.
+    #[cfg(test)]
+    pub(crate) fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Scans every command once, starting the jobs for any changes and any finished dependencies.
+    /// Jobs run in the background; call this repeatedly to keep watching.
+    pub fn tick(&mut self) -> Result<(), WitdErr> {
+        let mut is_state_changed = false;
+
+        // Restore the files seen in the last session so only changes since then trigger, and
+        // forget the commands that are gone so the state does not grow forever.
+        if !self.is_restored {
+            self.is_restored = true;
+            if let Some(path) = &self.state_path {
+                self.state = load_state(path, self.reporter.as_ref());
+            }
+            for context in self.contexts.iter_mut() {
+                if let Some(files) = self.state.get(&context.key) {
+                    context.witd.restore(files.clone());
+                }
+            }
+            let keys: Vec<String> = self.contexts.iter().map(|c| c.key.clone()).collect();
+            is_state_changed = self.state.retain(&keys);
+        }
+
+        // Contexts are sorted so every task comes after the tasks it depends on.
+        let clock = self.clock.as_ref();
+        let contexts = &mut self.contexts;
+        let reporter = &self.reporter;
+        let state = &mut self.state;
+
+        for i in 0..contexts.len() {
+            // A successful run triggers a full run of every task that depends on it, over every
+            // file the task has seen and in its own run mode.
+            if contexts[i].poll(clock, reporter) == Some(true) {
+                for dependent in contexts.iter_mut() {
+                    if dependent.dependencies.contains(&i) {
+                        reporter.report(Event::Triggered {
+                            task: dependent.witd.command().label(),
+                            path: None,
+                            change: None,
+                            reason: Reason::Dependency,
+                        });
+                        dependent.hold(Job {
+                            changes: BTreeMap::new(),
+                            files: dependent.witd.files(),
+                            run_mode: dependent.witd.command().run_mode(),
+                        });
+                    }
+                }
+            }
+
+            let is_ready = contexts[i]
+                .dependencies
+                .iter()
+                .all(|d| contexts[*d].is_ready_upstream());
+            let context = &mut contexts[i];
+
+            // The initial scan triggers on startup, unless files were restored from the last session.
+            let reason = match context.scanned.is_none() && state.get(&context.key).is_none() {
+                true => Reason::Startup,
+                false => Reason::Changed,
+            };
+
+            let files = context.scan(
+                self.file_source.as_ref(),
+                self.strict,
+                &self.ignored,
+                reporter.as_ref(),
+            )?;
+            if let Some(job) = context.witd.detect(files) {
+                for file in job.files.iter() {
+                    reporter.report(Event::Triggered {
+                        task: context.witd.command().label(),
+                        path: Some(file.path.clone()),
+                        change: job.changes.get(&file.path).copied(),
+                        reason,
+                    });
+                }
+                context.hold(job);
+            }
+
+            let snapshot = context.witd.snapshot();
+            if state.get(&context.key) != Some(&snapshot) {
+                state.set(context.key.clone(), snapshot);
+                is_state_changed = true;
+            }
+
+            if is_ready {
+                context.release(clock, reporter);
+            }
+        }
+
+        // A dry run must not record files as seen, since nothing was executed for them.
+        if let Some(path) = self.state_path.as_ref().filter(|_| !self.dry_run) {
+            if is_state_changed {
+                if let Err(e) = state.save(path) {
+                    reporter.report(Event::Warning {
+                        message: format!("Unable to save state: {}", e),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets every command to print what would be executed rather than executing it.
+    /// Files seen during a dry run are not persisted.
+    pub fn with_dry_run(mut self) -> Self {
+        self.dry_run = true;
+        for context in self.contexts.iter_mut() {
+            let executor = context.witd.executor().clone();
+            context.witd =
+                Witd::new(context.witd.command().clone().with_dry_run()).with_executor(executor);
+        }
+        self
+    }
+
+    /// Sets the clock that tells the time and runs jobs in the background.
+    #[cfg(test)]
+    pub(crate) fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sets the executor every command is executed with.
+    #[cfg(test)]
+    pub(crate) fn with_executor(mut self, executor: Arc<dyn Executor>) -> Self {
+        self.contexts = self
+            .contexts
+            .into_iter()
+            .map(|mut context| {
+                context.witd = context.witd.with_executor(executor.clone());
+                context
+            })
+            .collect();
+        self
+    }
+
+    /// Sets the source files are listed from.
+    #[cfg(test)]
+    pub(crate) fn with_file_source(mut self, file_source: Arc<dyn FileSource>) -> Self {
+        self.file_source = file_source;
+        self
+    }
+
+    /// Sets a path that is never watched, such as a file events are written to.
+    pub fn with_ignored(mut self, path: &str) -> Self {
+        if let Ok(path) = fs::canonicalize(path) {
+            self.ignored.push(path);
+        }
+        self
+    }
+
+    /// Sets the callback each event is passed to.
+    pub fn with_callback(self, callback: impl Fn(Event) + Send + Sync + 'static) -> Self {
+        self.with_reporter(Arc::new(callback))
+    }
+
+    /// Sets the reporter events are reported to.
+    pub fn with_reporter(mut self, reporter: Arc<dyn Reporter>) -> Self {
+        self.reporter = reporter;
+        self
+    }
+
+    /// Persists the files seen to `.witd/state`, so a later session only triggers for changes.
+    pub fn with_default_state(self) -> Self {
+        self.with_state(state::DEFAULT_PATH)
+    }
+
+    /// Sets the state restored on the first tick, as if loaded from a previous session.
+    #[cfg(test)]
+    pub(crate) fn with_restored(mut self, state: State) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Sets the path the files seen are persisted to, so a later session only triggers for changes.
+    pub fn with_state(mut self, path: &str) -> Self {
+        self.state_path = Some(path.to_string());
+        self
+    }
+
+    /// Sets file errors to be fatal rather than reported and skipped.
+    pub fn with_strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+}
+
+/// Returns whether the path is one of the ignored paths, comparing the canonical path only when
+/// the file names match so most files are never canonicalized.
+fn is_ignored(path: &str, ignored: &[PathBuf]) -> bool {
+    ignored.iter().any(|ignored| {
+        Path::new(path).file_name() == ignored.file_name()
+            && fs::canonicalize(path).is_ok_and(|p| &p == ignored)
+    })
+}
+
+/// Loads the state persisted by a previous session. Unreadable state is reported and ignored.
+fn load_state(path: &str, reporter: &dyn Reporter) -> State {
+    match State::load(path) {
+        Ok(state) => state,
+        Err(e) => {
+            reporter.report(Event::Warning {
+                message: format!("Ignoring state; {}", e.pretty_print()),
+            });
+            State::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::witd::{Change, TaskErr};
+    use std::sync::Mutex;
+
+    /// Creates a temporary directory containing the given files, unique to the test.
+    fn directory(name: &str, files: &[&str]) -> String {
+        let root =
+            std::env::temp_dir().join(format!("witd-runner-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        for file in files {
+            fs::write(root.join(file), "").unwrap();
+        }
+        root.display().to_string()
+    }
+
+    /// Returns a runner for the commands along with the events it reports.
+    fn runner(commands: &[&str]) -> (Runner, Arc<Mutex<Vec<Event>>>) {
+        let commands = commands
+            .iter()
+            .map(|c| Command::parse(c).unwrap())
+            .collect();
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = events.clone();
+        let runner = Runner::new(commands)
+            .unwrap()
+            .with_callback(move |event| recorded.lock().unwrap().push(event));
+
+        (runner, events)
+    }
+
+    describe!(new => {
+        #[test]
+        fn unknown_dependency_returns_err(){
+            let commands = vec![Command::parse("task test after build: directory . do echo end").unwrap()];
+            let expected = TaskErr::UnknownDependency{ task: "test".into(), dependency: "build".into() };
+            assert_eq!(Some(expected), Runner::new(commands).err());
+        }
+    });
+
+    describe!(run_once => {
+        #[test]
+        fn runs_tasks_in_order_and_skips_dependents_of_failures(){
+            let root = directory("once", &["a.txt"]);
+            let (mut runner, events) = runner(&[
+                &format!("task test after build: directory {} do true end", root),
+                &format!("task build: directory {} do false end", root),
+            ]);
+
+            let summary = runner.run_once().unwrap();
+            let _ = fs::remove_dir_all(&root);
+
+            let results: Vec<(String, TaskResult)> = summary.rows.iter().map(|r| (r.task.clone(), r.result)).collect();
+            assert_eq!(vec![("build".to_string(), TaskResult::Failed), ("test".to_string(), TaskResult::Skipped)], results);
+            assert!(events.lock().unwrap().contains(&Event::Scan{ task: "build".into(), files: 1, errors: 0 }));
+        }
+
+        #[test]
+        fn dry_run_reports_argv(){
+            let root = directory("dry-run", &["a.txt"]);
+            let (runner, events) = runner(&[&format!("foreach file in {} do echo NAME end", root)]);
+
+            let summary = runner.with_dry_run().run_once().unwrap();
+            let _ = fs::remove_dir_all(&root);
+
+            assert!(summary.passed());
+            let dry_runs: Vec<Event> = events.lock().unwrap().iter().filter(|e| matches!(e, Event::DryRun{..})).cloned().collect();
+            assert_eq!(vec![Event::DryRun{ task: format!("foreach file in {}", root), argv: vec!["echo".into(), "a.txt".into()], cwd: None }], dry_runs);
+        }
+    });
+
+    describe!(tick => {
+        /// Returns the paths and changes triggered, along with why.
+        fn triggered(events: &Mutex<Vec<Event>>) -> Vec<(Option<String>, Option<Change>, Reason)> {
+            events.lock().unwrap().iter().filter_map(|e| match e {
+                Event::Triggered{ path, change, reason, .. } => Some((path.clone(), *change, *reason)),
+                _ => None,
+            }).collect()
+        }
+
+        #[test]
+        fn reports_startup_files_then_changes(){
+            let root = directory("tick", &["a.txt"]);
+            let (mut runner, events) = runner(&[&format!("foreach file in {} do true end", root)]);
+
+            runner.tick().unwrap();
+            fs::write(format!("{}/b.txt", root), "").unwrap();
+            runner.tick().unwrap();
+            let _ = fs::remove_dir_all(&root);
+
+            let expected = vec![
+                (Some(format!("{}/a.txt", root)), Some(Change::Created), Reason::Startup),
+                (Some(format!("{}/b.txt", root)), Some(Change::Created), Reason::Changed),
+            ];
+            assert_eq!(expected, triggered(&events));
+        }
+
+        #[test]
+        fn ignored_paths_never_trigger(){
+            let root = directory("ignored", &["a.txt", "b.txt"]);
+            let (runner, events) = runner(&[&format!("foreach file in {} do true end", root)]);
+
+            let mut runner = runner.with_ignored(&format!("{}/a.txt", root));
+            runner.tick().unwrap();
+            let _ = fs::remove_dir_all(&root);
+
+            let expected = vec![(Some(format!("{}/b.txt", root)), Some(Change::Created), Reason::Startup)];
+            assert_eq!(expected, triggered(&events));
+        }
+
+        #[test]
+        fn forgets_commands_that_are_gone(){
+            let root = directory("forget", &["a.txt"]);
+            let (runner, _) = runner(&[&format!("foreach file in {} do true end", root)]);
+            let mut state = State::default();
+            state.set("gone".into(), std::collections::BTreeMap::new());
+
+            let mut runner = runner.with_restored(state);
+            runner.tick().unwrap();
+            let _ = fs::remove_dir_all(&root);
+
+            assert_eq!(None, runner.state().get("gone"));
+            assert!(runner.state().get(&runner.contexts[0].key).is_some());
+        }
+    });
+}
@@ -0,0 +1,624 @@
+use crate::{
+    clock::{Background, Clock},
+    list_all_files::{FileErr, FileSource},
+    runner::Runner,
+    types::{File, WalkOptions},
+    witd::{Command, RecordingExecutor, TaskErr},
+};
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+/// The time on a virtual clock, along with when each job started on it finishes.
+#[derive(Debug, Default)]
+struct Timeline {
+    /// The time each job that has not been joined finishes at, by its id.
+    jobs: BTreeMap<usize, Duration>,
+    job_duration: Duration,
+    next_id: usize,
+    now: Duration,
+}
+
+/// A clock that only moves when advanced. Jobs run as soon as they are spawned, but only finish
+/// once the clock reaches the end of the job duration.
+#[derive(Clone, Debug, Default)]
+pub struct VirtualClock {
+    timeline: Arc<Mutex<Timeline>>,
+}
+
+impl VirtualClock {
+    /// Creates a new clock at time zero, where jobs take no time.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the clock forward.
+    pub fn advance(&self, by: Duration) {
+        self.timeline().now += by;
+    }
+
+    /// Returns when the earliest job that has not been joined finishes, if there is one.
+    pub fn next_finish(&self) -> Option<Duration> {
+        self.timeline().jobs.values().min().copied()
+    }
+
+    /// Sets how long each job takes to run.
+    pub fn set_job_duration(&self, duration: Duration) {
+        self.timeline().job_duration = duration;
+    }
+
+    /// Locks the timeline; a panic elsewhere cannot leave it inconsistent.
+    fn timeline(&self) -> MutexGuard<'_, Timeline> {
+        self.timeline.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        self.timeline().now
+    }
+
+    fn spawn(&self, job: Box<dyn FnOnce() -> bool + Send>) -> Box<dyn Background> {
+        let succeeded = job();
+
+        let mut timeline = self.timeline();
+        let id = timeline.next_id;
+        let finishes_at = timeline.now + timeline.job_duration;
+        timeline.next_id += 1;
+        timeline.jobs.insert(id, finishes_at);
+
+        Box::new(VirtualJob {
+            clock: self.clone(),
+            finishes_at,
+            id,
+            succeeded,
+        })
+    }
+}
+
+/// A job spawned on a virtual clock, which has already run.
+struct VirtualJob {
+    clock: VirtualClock,
+    finishes_at: Duration,
+    id: usize,
+    succeeded: bool,
+}
+
+impl Background for VirtualJob {
+    fn is_finished(&self) -> bool {
+        self.clock.now() >= self.finishes_at
+    }
+
+    fn join(self: Box<Self>) -> bool {
+        self.clock.timeline().jobs.remove(&self.id);
+        self.succeeded
+    }
+}
+
+/// An in-memory file tree. Timestamps are truncated to the resolution, like a filesystem that
+/// only records whole seconds.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VirtualFs {
+    files: BTreeMap<String, File>,
+    resolution: Duration,
+}
+
+impl VirtualFs {
+    /// Creates a new empty file tree with exact timestamps.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the file at the given path, replacing it if it exists.
+    pub fn create(&mut self, path: &str, now: Duration) {
+        let now = self.timestamp(now);
+        let path_buf = Path::new(path);
+        let file = File {
+            created_at: Some(now),
+            directory: path_buf
+                .parent()
+                .map_or(String::new(), |p| p.display().to_string()),
+            extension: path_buf
+                .extension()
+                .map_or(String::new(), |e| e.to_string_lossy().to_string()),
+            modified_at: now,
+            name: path_buf
+                .file_name()
+                .map_or(String::new(), |n| n.to_string_lossy().to_string()),
+            path: path.to_string(),
+            root: String::new(),
+            target: path.to_string(),
+        };
+
+        self.files.insert(path.to_string(), file);
+    }
+
+    /// Deletes the file at the given path, if it exists.
+    pub fn delete(&mut self, path: &str) {
+        self.files.remove(path);
+    }
+
+    /// Lists every file under the root, the same way a walk of the disk would.
+    pub fn list(&self, root: &str, options: &WalkOptions) -> Vec<File> {
+        let mut files = vec![];
+        for file in self.files.values() {
+            let Ok(relative) = Path::new(&file.path).strip_prefix(root) else {
+                continue;
+            };
+
+            let components: Vec<Component> = relative.components().collect();
+            let depth = components.len();
+            let is_hidden = components
+                .iter()
+                .any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+
+            if options.max_depth.is_some_and(|max| depth > max)
+                || options.min_depth.is_some_and(|min| depth < min)
+                || (options.skip_hidden && is_hidden)
+            {
+                continue;
+            }
+
+            files.push(File {
+                root: root.to_string(),
+                ..file.clone()
+            });
+        }
+
+        files
+    }
+
+    /// Writes to the file at the given path, creating it if it does not exist.
+    pub fn modify(&mut self, path: &str, now: Duration) {
+        let now = self.timestamp(now);
+        match self.files.get_mut(path) {
+            Some(file) => file.modified_at = now,
+            None => self.create(path, now),
+        }
+    }
+
+    /// Sets the resolution timestamps are truncated to.
+    pub fn with_resolution(mut self, resolution: Duration) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    /// Returns the time truncated to the resolution.
+    fn timestamp(&self, now: Duration) -> Duration {
+        if self.resolution.is_zero() {
+            return now;
+        }
+
+        let ticks = now.as_nanos() / self.resolution.as_nanos();
+        Duration::from_nanos((ticks * self.resolution.as_nanos()) as u64)
+    }
+}
+
+/// A single invocation made during a simulation, along with when it was made.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Execution {
+    pub argv: Vec<String>,
+    pub at: Duration,
+}
+
+/// Lists the files in the tree; a virtual file never fails to be read.
+impl FileSource for Mutex<VirtualFs> {
+    fn list(&self, path: &str, options: &WalkOptions) -> Vec<Result<File, FileErr>> {
+        let fs = self.lock().unwrap_or_else(|e| e.into_inner());
+        fs.list(path, options).into_iter().map(Ok).collect()
+    }
+}
+
+/// Drives a runner through a scripted scenario against a virtual file tree and clock, recording
+/// every execution instead of running it. Each job takes the job duration of virtual time.
+pub struct Simulation {
+    clock: VirtualClock,
+    commands: Vec<Command>,
+    executions: Vec<Execution>,
+    executor: Arc<RecordingExecutor>,
+    fs: Arc<Mutex<VirtualFs>>,
+    runner: Runner,
+}
+
+impl Simulation {
+    /// Creates a new simulation of the commands, starting at time zero with no files.
+    pub fn new(commands: Vec<Command>) -> Result<Self, TaskErr> {
+        let clock = VirtualClock::new();
+        let executor = Arc::new(RecordingExecutor::new());
+        let fs = Arc::new(Mutex::new(VirtualFs::new()));
+        let runner = runner(commands.clone(), &clock, &executor, &fs)?;
+
+        Ok(Self {
+            clock,
+            commands,
+            executions: vec![],
+            executor,
+            fs,
+            runner,
+        })
+    }
+
+    /// Moves the clock forward without scanning.
+    pub fn advance(&mut self, by: Duration) -> &mut Self {
+        self.clock.advance(by);
+        self
+    }
+
+    /// Creates the file at the given path at the current time.
+    pub fn create(&mut self, path: &str) -> &mut Self {
+        self.fs().create(path, self.clock.now());
+        self
+    }
+
+    /// Deletes the file at the given path.
+    pub fn delete(&mut self, path: &str) -> &mut Self {
+        self.fs().delete(path);
+        self
+    }
+
+    /// Returns every execution made so far.
+    pub fn executions(&self) -> &[Execution] {
+        &self.executions
+    }
+
+    /// Writes to the file at the given path at the current time.
+    pub fn modify(&mut self, path: &str) -> &mut Self {
+        self.fs().modify(path, self.clock.now());
+        self
+    }
+
+    /// Simulates stopping and starting again, restoring the files seen the way persisted state does.
+    /// Any running or queued job is lost.
+    pub fn restart(&mut self) -> &mut Self {
+        let state = self.runner.state().clone();
+        self.runner = runner(self.commands.clone(), &self.clock, &self.executor, &self.fs)
+            .expect("the commands were sorted when the simulation was created")
+            .with_restored(state);
+        self.clock.timeline().jobs.clear();
+        self
+    }
+
+    /// Advances the clock and scans until no job is running or queued.
+    pub fn settle(&mut self) -> &mut Self {
+        self.tick();
+        while let Some(at) = self.clock.next_finish() {
+            self.clock.advance(at.saturating_sub(self.clock.now()));
+            self.tick();
+        }
+        self
+    }
+
+    /// Scans once like the runner does, finishing any job whose time is up and starting or
+    /// queueing jobs for any changes.
+    pub fn tick(&mut self) -> &mut Self {
+        // Only strict runners fail, and the virtual file tree has no errors to fail on.
+        let _ = self.runner.tick();
+
+        let at = self.clock.now();
+        let invocations = self.executor.invocations();
+        for invocation in invocations.into_iter().skip(self.executions.len()) {
+            self.executions.push(Execution {
+                argv: invocation.argv,
+                at,
+            });
+        }
+        self
+    }
+
+    /// Sets the exit code of every execution of the given program. Replaces the runner, so this
+    /// must be set before the simulation starts.
+    pub fn with_exit_code(self, program: &str, code: i32) -> Self {
+        let Self {
+            clock,
+            commands,
+            executions,
+            executor,
+            fs,
+            runner: previous,
+        } = self;
+
+        // Once the runner is dropped nothing else holds the executor, so its exit codes are kept.
+        drop(previous);
+        let executor = Arc::try_unwrap(executor).unwrap_or_default();
+        let executor = Arc::new(executor.with_exit_code(program, code));
+        let runner = runner(commands.clone(), &clock, &executor, &fs)
+            .expect("the commands were sorted when the simulation was created");
+
+        Self {
+            clock,
+            commands,
+            executions,
+            executor,
+            fs,
+            runner,
+        }
+    }
+
+    /// Sets how long each job takes to run.
+    pub fn with_job_duration(self, duration: Duration) -> Self {
+        self.clock.set_job_duration(duration);
+        self
+    }
+
+    /// Sets the resolution file timestamps are truncated to.
+    pub fn with_resolution(self, resolution: Duration) -> Self {
+        let fs = std::mem::take(&mut *self.fs());
+        *self.fs() = fs.with_resolution(resolution);
+        self
+    }
+
+    /// Locks the file tree.
+    fn fs(&self) -> MutexGuard<'_, VirtualFs> {
+        self.fs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Creates a runner for the commands that is driven by the simulation, ignoring its events.
+fn runner(
+    commands: Vec<Command>,
+    clock: &VirtualClock,
+    executor: &Arc<RecordingExecutor>,
+    fs: &Arc<Mutex<VirtualFs>>,
+) -> Result<Runner, TaskErr> {
+    Ok(Runner::new(commands)?
+        .with_callback(|_| {})
+        .with_clock(Arc::new(clock.clone()))
+        .with_executor(executor.clone())
+        .with_file_source(fs.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: f64) -> Duration {
+        Duration::from_secs_f64(secs)
+    }
+
+    fn simulation(commands: &[&str]) -> Simulation {
+        Simulation::new(
+            commands
+                .iter()
+                .map(|c| Command::parse(c).unwrap())
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    /// Returns the argv of every execution with the time it was made, for concise assertions.
+    fn executions(simulation: &Simulation) -> Vec<(f64, String)> {
+        simulation
+            .executions()
+            .iter()
+            .map(|e| (e.at.as_secs_f64(), e.argv.join(" ")))
+            .collect()
+    }
+
+    fn execution(at: f64, argv: &str) -> (f64, String) {
+        (at, argv.to_string())
+    }
+
+    describe!(list => {
+        #[test]
+        fn returns_files_under_root(){
+            let mut fs = VirtualFs::new();
+            fs.create("./src/a.rs", secs(1.0));
+            fs.create("./tests/b.rs", secs(1.0));
+
+            let files = fs.list("./src", &WalkOptions::default());
+
+            let expected = File{
+                created_at: Some(secs(1.0)),
+                directory: "./src".into(),
+                extension: "rs".into(),
+                modified_at: secs(1.0),
+                name: "a.rs".into(),
+                path: "./src/a.rs".into(),
+                root: "./src".into(),
+                target: "./src/a.rs".into(),
+            };
+            assert_eq!(vec![expected], files);
+        }
+
+        #[test]
+        fn applies_walk_options(){
+            let mut fs = VirtualFs::new();
+            for path in ["./a", "./.b", "./c/d", "./c/e/f"]{
+                fs.create(path, secs(1.0));
+            }
+
+            let paths = |options: WalkOptions| -> Vec<String> { fs.list(".", &options).into_iter().map(|f| f.path).collect() };
+            assert_eq!(vec!["./a", "./c/d", "./c/e/f"], paths(WalkOptions{ skip_hidden: true, ..Default::default() }));
+            assert_eq!(vec!["./.b", "./a", "./c/d"], paths(WalkOptions{ max_depth: Some(2), ..Default::default() }));
+            assert_eq!(vec!["./c/d", "./c/e/f"], paths(WalkOptions{ min_depth: Some(2), ..Default::default() }));
+        }
+    });
+
+    describe!(modify => {
+        #[test]
+        fn truncates_to_resolution(){
+            let mut fs = VirtualFs::new().with_resolution(secs(1.0));
+            fs.create("./a", secs(1.2));
+            fs.modify("./a", secs(2.7));
+
+            let file = &fs.list(".", &WalkOptions::default())[0];
+            assert_eq!((Some(secs(1.0)), secs(2.0)), (file.created_at, file.modified_at));
+        }
+    });
+
+    describe!(tick => {
+        #[test]
+        fn startup_executes_existing_files(){
+            let mut simulation = simulation(&["foreach file in . do echo PATH end"]);
+            simulation.create("./a").create("./b").tick();
+
+            assert_eq!(vec![execution(0.0, "echo ./a"), execution(0.0, "echo ./b")], executions(&simulation));
+        }
+
+        #[test]
+        fn create_and_modify_execute_changed_file(){
+            let mut simulation = simulation(&["foreach file in . do echo PATH end"]);
+            simulation.create("./a").tick();
+            simulation.advance(secs(1.0)).create("./b").tick();
+            simulation.advance(secs(1.0)).modify("./a").tick();
+            simulation.advance(secs(1.0)).tick();
+
+            let expected = vec![execution(0.0, "echo ./a"), execution(1.0, "echo ./b"), execution(2.0, "echo ./a")];
+            assert_eq!(expected, executions(&simulation));
+        }
+
+        #[test]
+        fn delete_does_not_execute(){
+            let mut simulation = simulation(&["foreach file in . do echo PATH end"]);
+            simulation.create("./a").create("./b").tick();
+            simulation.advance(secs(1.0)).delete("./a").tick();
+
+            assert_eq!(2, simulation.executions().len());
+        }
+
+        #[test]
+        fn recreated_file_executes(){
+            let mut simulation = simulation(&["foreach file in . do echo PATH end"]);
+            simulation.create("./a").tick();
+            simulation.advance(secs(1.0)).delete("./a").tick();
+            simulation.advance(secs(1.0)).create("./a").tick();
+
+            assert_eq!(vec![execution(0.0, "echo ./a"), execution(2.0, "echo ./a")], executions(&simulation));
+        }
+
+        #[test]
+        fn same_second_write_is_missed_at_coarse_resolution(){
+            let mut simulation = simulation(&["foreach file in . do echo PATH end"]).with_resolution(secs(1.0));
+            simulation.advance(secs(0.2)).create("./a").tick();
+            simulation.advance(secs(0.5)).modify("./a").tick();
+
+            assert_eq!(vec![execution(0.2, "echo ./a")], executions(&simulation));
+        }
+
+        #[test]
+        fn same_second_write_executes_at_fine_resolution(){
+            let mut simulation = simulation(&["foreach file in . do echo PATH end"]).with_resolution(secs(0.001));
+            simulation.advance(secs(0.2)).create("./a").tick();
+            simulation.advance(secs(0.5)).modify("./a").tick();
+
+            assert_eq!(vec![execution(0.2, "echo ./a"), execution(0.7, "echo ./a")], executions(&simulation));
+        }
+
+        #[test]
+        fn burst_between_scans_executes_once(){
+            let mut simulation = simulation(&["directory . do echo FILES end"]);
+            simulation.tick();
+            for _ in 0..5 {
+                simulation.advance(secs(0.1)).modify("./a").modify("./b");
+            }
+            simulation.tick();
+
+            assert_eq!(vec![execution(0.5, "echo ./a ./b")], executions(&simulation));
+        }
+
+        #[test]
+        fn burst_while_running_queues_single_rerun(){
+            let mut simulation = simulation(&["foreach file in . do echo PATH end"]).with_job_duration(secs(10.0));
+            simulation.create("./a").tick();
+            for path in ["./b", "./a", "./b"]{
+                simulation.advance(secs(1.0)).modify(path).tick();
+            }
+            simulation.settle();
+
+            let expected = vec![execution(0.0, "echo ./a"), execution(10.0, "echo ./b"), execution(10.0, "echo ./a")];
+            assert_eq!(expected, executions(&simulation));
+        }
+
+        #[test]
+        fn change_after_job_finishes_starts_new_job(){
+            let mut simulation = simulation(&["foreach file in . do echo PATH end"]).with_job_duration(secs(2.0));
+            simulation.create("./a").tick();
+            simulation.advance(secs(3.0)).modify("./a").tick();
+
+            assert_eq!(vec![execution(0.0, "echo ./a"), execution(3.0, "echo ./a")], executions(&simulation));
+        }
+
+        #[test]
+        fn where_clause_only_executes_matching_files(){
+            let mut simulation = simulation(&["foreach file in . where EXT in [rs] do echo PATH end"]);
+            simulation.create("./a.rs").create("./b.md").tick();
+            simulation.advance(secs(1.0)).modify("./b.md").modify("./a.rs").tick();
+
+            assert_eq!(vec![execution(0.0, "echo ./a.rs"), execution(1.0, "echo ./a.rs")], executions(&simulation));
+        }
+
+        #[test]
+        fn startup_none_only_executes_later_changes(){
+            let mut simulation = simulation(&["foreach file in . startup none do echo PATH end"]);
+            simulation.create("./a").tick();
+            simulation.advance(secs(1.0)).modify("./a").tick();
+
+            assert_eq!(vec![execution(1.0, "echo ./a")], executions(&simulation));
+        }
+    });
+
+    describe!(settle => {
+        #[test]
+        fn dependency_reruns_foreach_file_dependent_per_file(){
+            let mut simulation = simulation(&[
+                "task build: directory ./src do make end",
+                "task fmt after build: foreach file in ./src do fmt PATH end",
+            ]).with_job_duration(secs(1.0));
+            simulation.create("./src/a.rs").create("./src/b.rs").settle();
+            simulation.advance(secs(1.0)).modify("./src/a.rs").settle();
+
+            let expected = vec![
+                execution(0.0, "make"),
+                execution(1.0, "fmt ./src/a.rs"),
+                execution(1.0, "fmt ./src/b.rs"),
+                execution(3.0, "make"),
+                execution(4.0, "fmt ./src/a.rs"),
+                execution(4.0, "fmt ./src/b.rs"),
+            ];
+            assert_eq!(expected, executions(&simulation));
+        }
+    });
+
+    describe!(restart => {
+        #[test]
+        fn only_executes_changes_since_last_session(){
+            let mut simulation = simulation(&["foreach file in . do echo PATH end"]);
+            simulation.create("./a").create("./b").settle();
+            simulation.restart();
+            simulation.advance(secs(1.0)).modify("./b").create("./c").tick();
+
+            let expected = vec![execution(0.0, "echo ./a"), execution(0.0, "echo ./b"), execution(1.0, "echo ./b"), execution(1.0, "echo ./c")];
+            assert_eq!(expected, executions(&simulation));
+        }
+
+        #[test]
+        fn unchanged_files_do_not_execute(){
+            let mut simulation = simulation(&["foreach file in . do echo PATH end"]);
+            simulation.create("./a").settle();
+            simulation.restart().tick();
+
+            assert_eq!(1, simulation.executions().len());
+        }
+
+        #[test]
+        fn unfinished_jobs_execute_again(){
+            let mut simulation = simulation(&["foreach file in . do echo PATH end"]).with_job_duration(secs(10.0));
+            simulation.create("./a").tick();
+            simulation.advance(secs(1.0)).restart().tick();
+
+            assert_eq!(vec![execution(0.0, "echo ./a"), execution(1.0, "echo ./a")], executions(&simulation));
+        }
+
+        #[test]
+        fn failed_jobs_execute_again(){
+            let mut simulation = simulation(&["foreach file in . do lint PATH end"]).with_exit_code("lint", 1);
+            simulation.create("./a").settle();
+            simulation.advance(secs(1.0)).restart().tick();
+
+            assert_eq!(vec![execution(0.0, "lint ./a"), execution(1.0, "lint ./a")], executions(&simulation));
+        }
+    });
+}
@@ -0,0 +1,320 @@
+use crate::{traits::PrettyPrint, types::File};
+use std::{collections::BTreeMap, fs, io, path::Path, time::Duration};
+
+/// The path the state is persisted to between sessions.
+pub const DEFAULT_PATH: &str = ".witd/state";
+
+/// The directory the state is persisted in.
+const DIRECTORY: &str = ".witd";
+
+/// The header of the state file; state written by another version is ignored.
+const HEADER: &str = "witd-state 1";
+
+/// An error that may occur when loading the state.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StateErr {
+    Corrupt { path: String, line: usize },
+    Read { path: String, kind: io::ErrorKind },
+    Version { path: String },
+}
+
+impl PrettyPrint for StateErr {
+    fn pretty_print(&self) -> String {
+        match self {
+            StateErr::Corrupt { path, line } => format!("{}:{}: Corrupt state!", path, line),
+            StateErr::Read { path, kind } => format!("Unable to read '{}': {}", path, kind),
+            StateErr::Version { path } => {
+                format!("'{}' was written by another version of witd.", path)
+            }
+        }
+    }
+}
+
+/// The recorded timestamps of a file, used to tell whether it changed between sessions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FileState {
+    pub created_at: Option<Duration>,
+    pub modified_at: Duration,
+}
+
+impl From<&File> for FileState {
+    fn from(file: &File) -> Self {
+        Self {
+            created_at: file.created_at,
+            modified_at: file.modified_at,
+        }
+    }
+}
+
+/// The files seen by each command, keyed by the command's hash.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct State {
+    commands: BTreeMap<String, BTreeMap<String, FileState>>,
+}
+
+impl State {
+    /// Returns the files recorded for the command with the given key.
+    pub fn get(&self, key: &str) -> Option<&BTreeMap<String, FileState>> {
+        self.commands.get(key)
+    }
+
+    /// Loads the state from the given path. A missing file is an empty state.
+    pub fn load(path: &str) -> Result<Self, StateErr> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::parse(path, &contents),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(StateErr::Read {
+                path: path.to_string(),
+                kind: e.kind(),
+            }),
+        }
+    }
+
+    /// Parses the state from the contents of a state file.
+    pub fn parse(path: &str, contents: &str) -> Result<Self, StateErr> {
+        let mut lines = contents.lines().enumerate();
+        if lines.next().map(|(_, l)| l) != Some(HEADER) {
+            return Err(StateErr::Version {
+                path: path.to_string(),
+            });
+        }
+
+        let mut state = Self::default();
+        let mut files = None;
+        for (i, line) in lines {
+            let corrupt = || StateErr::Corrupt {
+                path: path.to_string(),
+                line: i + 1,
+            };
+
+            if let Some(key) = line.strip_prefix("command ") {
+                files = Some(state.commands.entry(key.to_string()).or_default());
+                continue;
+            }
+
+            // Each file is `MODIFIED CREATED PATH`, with `-` for an unknown creation time.
+            let mut parts = line.splitn(3, ' ');
+            let (Some(modified_at), Some(created_at), Some(file_path)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                return Err(corrupt());
+            };
+
+            let modified_at = modified_at.parse::<u64>().map_err(|_| corrupt())?;
+            let created_at = match created_at {
+                "-" => None,
+                nanos => Some(nanos.parse::<u64>().map_err(|_| corrupt())?),
+            };
+
+            files.as_mut().ok_or_else(corrupt)?.insert(
+                unescape(file_path),
+                FileState {
+                    created_at: created_at.map(Duration::from_nanos),
+                    modified_at: Duration::from_nanos(modified_at),
+                },
+            );
+        }
+
+        Ok(state)
+    }
+
+    /// Drops the files of every command whose key is not given, returning whether any were dropped.
+    pub fn retain(&mut self, keys: &[String]) -> bool {
+        let before = self.commands.len();
+        self.commands.retain(|key, _| keys.contains(key));
+        self.commands.len() != before
+    }
+
+    /// Saves the state to the given path, creating its directory if needed.
+    /// The state is written to a temporary file first so a crash never leaves it half written.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temporary = format!("{}.tmp", path);
+        fs::write(&temporary, self.serialize())?;
+        fs::rename(&temporary, path)
+    }
+
+    /// Records the files for the command with the given key.
+    pub fn set(&mut self, key: String, files: BTreeMap<String, FileState>) {
+        self.commands.insert(key, files);
+    }
+
+    /// Returns the contents of the state file.
+    fn serialize(&self) -> String {
+        let mut lines = vec![HEADER.to_string()];
+        for (key, files) in self.commands.iter() {
+            lines.push(format!("command {}", key));
+            for (path, file) in files.iter() {
+                lines.push(format!(
+                    "{} {} {}",
+                    file.modified_at.as_nanos(),
+                    file.created_at
+                        .map_or("-".to_string(), |c| c.as_nanos().to_string()),
+                    escape(path)
+                ));
+            }
+        }
+
+        lines.join("\n") + "\n"
+    }
+}
+
+/// Returns whether the path is within a state directory, so saving the state never triggers commands.
+pub fn is_state_path(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .any(|c| c.as_os_str() == DIRECTORY)
+}
+
+/// Escapes backslashes and newlines so the path fits on one line.
+fn escape(path: &str) -> String {
+    path.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// Reverses `escape`.
+fn unescape(path: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                unescaped.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                unescaped.push('\\');
+                chars.next();
+            }
+            (c, _) => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> State {
+        let mut state = State::default();
+        state.set(
+            "abc".into(),
+            BTreeMap::from([
+                (
+                    "./a b.rs".to_string(),
+                    FileState {
+                        created_at: Some(Duration::from_nanos(5)),
+                        modified_at: Duration::from_nanos(10),
+                    },
+                ),
+                (
+                    "./c\\d\ne.rs".to_string(),
+                    FileState {
+                        created_at: None,
+                        modified_at: Duration::from_nanos(20),
+                    },
+                ),
+            ]),
+        );
+        state.set("def".into(), BTreeMap::new());
+        state
+    }
+
+    describe!(is_state_path => {
+        #[test]
+        fn state_returns_true(){
+            assert!(is_state_path(DEFAULT_PATH));
+            assert!(is_state_path("./crates/a/.witd/state.tmp"));
+        }
+
+        #[test]
+        fn other_returns_false(){
+            assert!(!is_state_path("./src/.witdfile"));
+        }
+    });
+
+    describe!(load => {
+        #[test]
+        fn missing_file_returns_empty(){
+            assert_eq!(Ok(State::default()), State::load("./does/not/exist/state"));
+        }
+
+        #[test]
+        fn save_and_load_round_trips(){
+            let root = std::env::temp_dir().join(format!("witd-state-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            let path = root.join(".witd/state").display().to_string();
+
+            state().save(&path).unwrap();
+            let actual = State::load(&path);
+            let _ = fs::remove_dir_all(&root);
+
+            assert_eq!(Ok(state()), actual);
+        }
+    });
+
+    describe!(parse => {
+        #[test]
+        fn parses_serialized(){
+            assert_eq!(Ok(state()), State::parse("state", &state().serialize()));
+        }
+
+        #[test]
+        fn other_version_returns_err(){
+            let expected = Err(StateErr::Version{ path: "state".into() });
+            assert_eq!(expected, State::parse("state", "witd-state 0\ncommand abc\n"));
+            assert_eq!(expected, State::parse("state", ""));
+        }
+
+        #[test]
+        fn corrupt_lines_return_err(){
+            let expected = Err(StateErr::Corrupt{ path: "state".into(), line: 3 });
+            assert_eq!(expected, State::parse("state", "witd-state 1\ncommand abc\n10 garbage ./a.rs\n"));
+        }
+
+        #[test]
+        fn file_before_command_returns_err(){
+            let expected = Err(StateErr::Corrupt{ path: "state".into(), line: 2 });
+            assert_eq!(expected, State::parse("state", "witd-state 1\n10 - ./a.rs\n"));
+        }
+    });
+
+    describe!(retain => {
+        #[test]
+        fn drops_other_keys(){
+            let mut state = state();
+            assert!(state.retain(&["def".into(), "ghi".into()]));
+            assert_eq!(None, state.get("abc"));
+            assert_eq!(Some(&BTreeMap::new()), state.get("def"));
+        }
+
+        #[test]
+        fn keeping_every_key_returns_false(){
+            let mut actual = state();
+            assert!(!actual.retain(&["abc".into(), "def".into()]));
+            assert_eq!(state(), actual);
+        }
+    });
+
+    describe!(pretty_print => {
+        #[test]
+        fn corrupt(){
+            assert_eq!("state:3: Corrupt state!", StateErr::Corrupt{ path: "state".into(), line: 3 }.pretty_print());
+        }
+
+        #[test]
+        fn read(){
+            let e = StateErr::Read{ path: "state".into(), kind: io::ErrorKind::PermissionDenied };
+            assert_eq!(format!("Unable to read 'state': {}", io::ErrorKind::PermissionDenied), e.pretty_print());
+        }
+
+        #[test]
+        fn version(){
+            assert_eq!("'state' was written by another version of witd.", StateErr::Version{ path: "state".into() }.pretty_print());
+        }
+    });
+}
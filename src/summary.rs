@@ -0,0 +1,125 @@
+use crate::traits::PrettyPrint;
+use std::time::Duration;
+
+/// The result of running a task once.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TaskResult {
+    Failed,
+    Passed,
+    /// A task it depends on did not pass, so it was not run.
+    Skipped,
+}
+
+impl PrettyPrint for TaskResult {
+    fn pretty_print(&self) -> String {
+        match self {
+            TaskResult::Failed => "failed",
+            TaskResult::Passed => "passed",
+            TaskResult::Skipped => "skipped",
+        }
+        .into()
+    }
+}
+
+/// A single row of the summary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SummaryRow {
+    pub duration: Option<Duration>,
+    pub result: TaskResult,
+    pub task: String,
+}
+
+/// The results of every task after running them once.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub rows: Vec<SummaryRow>,
+}
+
+impl Summary {
+    /// Returns whether every task passed.
+    pub fn passed(&self) -> bool {
+        self.rows.iter().all(|r| r.result == TaskResult::Passed)
+    }
+}
+
+impl PrettyPrint for Summary {
+    fn pretty_print(&self) -> String {
+        let width = self
+            .rows
+            .iter()
+            .map(|r| r.task.len())
+            .chain(["TASK".len()])
+            .max()
+            .unwrap_or_default();
+
+        let mut lines = vec![format!("{:width$}  {:7}  TIME", "TASK", "RESULT")];
+        for row in self.rows.iter() {
+            let duration = row
+                .duration
+                .map_or("-".to_string(), |d| format!("{:.2}s", d.as_secs_f64()));
+            lines.push(format!(
+                "{:width$}  {:7}  {}",
+                row.task,
+                row.result.pretty_print(),
+                duration
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(task: &str, result: TaskResult, duration: Option<Duration>) -> SummaryRow {
+        SummaryRow {
+            duration,
+            result,
+            task: task.into(),
+        }
+    }
+
+    describe!(passed => {
+        #[test]
+        fn all_passed_returns_true(){
+            let summary = Summary{ rows: vec![row("build", TaskResult::Passed, None)] };
+            assert!(summary.passed());
+        }
+
+        #[test]
+        fn failed_or_skipped_returns_false(){
+            for result in [TaskResult::Failed, TaskResult::Skipped]{
+                let summary = Summary{ rows: vec![row("build", TaskResult::Passed, None), row("test", result, None)] };
+                assert!(!summary.passed());
+            }
+        }
+    });
+
+    describe!(pretty_print => {
+        #[test]
+        fn aligns_columns(){
+            let summary = Summary{ rows: vec![
+                row("build", TaskResult::Passed, Some(Duration::from_millis(1200))),
+                row("integration", TaskResult::Failed, Some(Duration::from_millis(50))),
+                row("deploy", TaskResult::Skipped, None),
+            ]};
+
+            let expected = [
+                "TASK         RESULT   TIME",
+                "build        passed   1.20s",
+                "integration  failed   0.05s",
+                "deploy       skipped  -",
+            ].join("\n");
+            assert_eq!(expected, summary.pretty_print());
+        }
+
+        #[test]
+        fn results(){
+            assert_eq!("failed", TaskResult::Failed.pretty_print());
+            assert_eq!("passed", TaskResult::Passed.pretty_print());
+            assert_eq!("skipped", TaskResult::Skipped.pretty_print());
+        }
+    });
+}
@@ -0,0 +1,19 @@
+#[macro_use]
+mod describe;
+
+use crate::types::File;
+use std::time::Duration;
+
+/// Returns a file at the given path, directly under the root `.`, last modified at one second.
+pub fn file(path: &str) -> File {
+    File {
+        created_at: None,
+        directory: ".".into(),
+        extension: "".into(),
+        modified_at: Duration::from_secs(1),
+        name: path.into(),
+        path: path.into(),
+        root: ".".into(),
+        target: path.into(),
+    }
+}
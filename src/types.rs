@@ -1,14 +1,20 @@
-use std::time::Duration;
+use std::{path::Path, time::Duration};
 
 /// A simple struct that represents a file.
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub struct File {
-    pub created_at: Duration,
+    /// The birth time of the file; `None` on filesystems that do not record it.
+    pub created_at: Option<Duration>,
     pub directory: String,
     pub extension: String,
     pub modified_at: Duration,
     pub name: String,
+    /// The path the file was found at; for symlinks this is the link itself.
     pub path: String,
+    /// The root path the file was found under.
+    pub root: String,
+    /// The resolved path of the file once all symlinks are followed.
+    pub target: String,
 }
 
 impl File {
@@ -16,6 +22,27 @@ impl File {
     pub fn is_older(&self, other: &Self) -> bool {
         self.created_at < other.created_at || self.modified_at < other.modified_at
     }
+
+    /// Returns the path of the file relative to its root.
+    pub fn relative_path(&self) -> String {
+        match Path::new(&self.path).strip_prefix(&self.root) {
+            Ok(p) => p.to_string_lossy().to_string(),
+            Err(_) => self.path.clone(),
+        }
+    }
+}
+
+/// Options that control how a directory is traversed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WalkOptions {
+    /// Whether symlinks are followed. Loops are detected and reported as errors.
+    pub follow_links: bool,
+    /// The maximum depth to descend to, where files directly under the root are at depth 1.
+    pub max_depth: Option<usize>,
+    /// The minimum depth a file must be at to be returned.
+    pub min_depth: Option<usize>,
+    /// Whether dotfiles and dot-directories are skipped.
+    pub skip_hidden: bool,
 }
 
 #[cfg(test)]
@@ -24,12 +51,14 @@ mod tests {
 
     fn file() -> File {
         File {
-            created_at: Duration::from_secs_f32(3.9),
+            created_at: Some(Duration::from_secs_f32(3.9)),
             directory: "Test//test".into(),
             extension: "ext".into(),
             modified_at: Duration::from_secs_f32(3.9),
             name: "test".into(),
             path: "test".into(),
+            root: "".into(),
+            target: "test".into(),
         }
     }
 
@@ -39,31 +68,47 @@ mod tests {
             let a = file();
             let b = a.clone();
 
-            assert_eq!(false, a.is_older(&b));
+            assert!(!a.is_older(&b));
         }
 
         #[test]
         fn created_at_younger_than_other_returns_false(){
             let a = file();
             let mut b = a.clone();
-            b.created_at -= Duration::from_nanos(444);
-            assert_eq!(false, a.is_older(&b));
+            b.created_at = b.created_at.map(|c| c - Duration::from_nanos(444));
+            assert!(!a.is_older(&b));
         }
 
         #[test]
         fn created_at_older_than_other_returns_true(){
             let a = file();
             let mut b = a.clone();
-            b.created_at += Duration::from_nanos(444);
-            assert_eq!(true, a.is_older(&b));
+            b.created_at = b.created_at.map(|c| c + Duration::from_nanos(444));
+            assert!(a.is_older(&b));
+        }
+
+        #[test]
+        fn created_at_missing_on_both_returns_false(){
+            let mut a = file();
+            a.created_at = None;
+            let b = a.clone();
+            assert!(!a.is_older(&b));
+        }
+
+        #[test]
+        fn created_at_becoming_available_returns_true(){
+            let mut a = file();
+            a.created_at = None;
+            let b = file();
+            assert!(a.is_older(&b));
         }
 
-          #[test]
+        #[test]
         fn modified_at_younger_than_other_returns_false(){
             let a = file();
             let mut b = a.clone();
             b.modified_at -= Duration::from_nanos(444);
-            assert_eq!(false, a.is_older(&b));
+            assert!(!a.is_older(&b));
         }
 
         #[test]
@@ -71,7 +116,33 @@ mod tests {
             let a = file();
             let mut b = a.clone();
             b.modified_at += Duration::from_nanos(444);
-            assert_eq!(true, a.is_older(&b));
+            assert!(a.is_older(&b));
+        }
+    });
+
+    describe!(relative_path => {
+        #[test]
+        fn strips_root(){
+            let mut a = file();
+            a.path = "./src/witd/mod.rs".into();
+            a.root = "./src".into();
+            assert_eq!("witd/mod.rs", a.relative_path());
+        }
+
+        #[test]
+        fn strips_root_with_trailing_slash(){
+            let mut a = file();
+            a.path = "./src/main.rs".into();
+            a.root = "./src/".into();
+            assert_eq!("main.rs", a.relative_path());
+        }
+
+        #[test]
+        fn outside_root_returns_path(){
+            let mut a = file();
+            a.path = "./tests/a.rs".into();
+            a.root = "./src".into();
+            assert_eq!("./tests/a.rs", a.relative_path());
         }
     });
 }
@@ -1,117 +1,309 @@
-use super::{CommandErr, RunMode, ScriptOptions};
-use crate::{traits::PrettyPrint, types::File, witd::Keyword};
-use std::process;
-
-/// A command that may be executed.
+use super::{
+    Change, CommandErr, Event, Executor, Invocation, Job, Outcome, Reporter, RunMode,
+    ScriptOptions, StartupMode, StdinFormat, Stream, Trigger,
+};
+use crate::{
+    env_file::{self, EnvFileErr},
+    traits::PrettyPrint,
+    types::{File, WalkOptions},
+    witd::Keyword,
+};
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Component, Path, PathBuf},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+    time::Instant,
+};
+
+/// A command that may be executed, parsed from its syntax or built with a `CommandBuilder`.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Command {
-    command: String,
-    root_path: String,
-    run_mode: RunMode,
+    pub(super) chunk_size: Option<usize>,
+    pub(super) cwd: Option<String>,
+    pub(super) dependencies: Vec<String>,
+    pub(super) dry_run: bool,
+    pub(super) env_file: Option<String>,
+    pub(super) environment: BTreeMap<String, String>,
+    /// The values each script option must have for a file to be watched, from `where` clauses.
+    pub(super) filters: Vec<(ScriptOptions, Vec<String>)>,
+    pub(super) name: Option<String>,
+    pub(super) on_failure: Vec<String>,
+    pub(super) on_success: Vec<String>,
+    pub(super) parallelism: usize,
+    pub(super) root_paths: Vec<String>,
+    pub(super) run_mode: RunMode,
+    pub(super) startup_mode: StartupMode,
+    pub(super) stdin_format: Option<StdinFormat>,
+    pub(super) steps: Vec<String>,
+    pub(super) walk_options: WalkOptions,
 }
 impl Command {
     /// Parses the given command.
     pub fn parse(s: &str) -> Result<Self, CommandErr> {
+        Self::parse_at(s).map_err(|(e, _)| e)
+    }
+
+    /// Parses the given command. On error, also returns the byte offset into the trimmed input
+    /// where the error was found, so it can be mapped back to where the command was written.
+    pub(crate) fn parse_at(s: &str) -> Result<Self, (CommandErr, usize)> {
+        let input = s.trim();
+        let mut rest = input.to_string();
+        Self::parse_rest(&mut rest).map_err(|e| {
+            // A missing `end` is only noticed once the input runs out.
+            let offset = match e {
+                CommandErr::MissingKeywordEnd => input.len(),
+                _ => input.len() - rest.len(),
+            };
+            (e, offset)
+        })
+    }
+
+    /// Parses the command, advancing `s` past each part as it is parsed so that on error it
+    /// begins with the part that failed.
+    fn parse_rest(s: &mut String) -> Result<Self, CommandErr> {
         // Check if empty input
-        if s.trim().len() == 0 {
+        if s.is_empty() {
             return Err(CommandErr::EmptyInput);
         }
 
-        // Calculate run mode
-        let (run_mode, s) = {
-            let file_mode = format!("foreach {} in", RunMode::File.pretty_print());
-            let directory_mode = RunMode::Directory.pretty_print();
-
-            let is_file_mode = s.starts_with(&file_mode);
-            let is_directory_mode = s.starts_with(&directory_mode);
-
-            if !is_file_mode && !is_directory_mode {
-                return Err(CommandErr::MissingRunMode);
+        // Parse task
+        let (name, dependencies) = {
+            let (token, rest) = split_token(s);
+            if Keyword::parse(&token) == Some(Keyword::Task) {
+                let (header, rest) = rest
+                    .split_once(':')
+                    .ok_or(CommandErr::MissingTaskSeparator)?;
+                let (name, dependencies) = parse_task_header(header)?;
+
+                *s = rest.trim().to_string();
+                (Some(name), dependencies)
+            } else {
+                (None, vec![])
             }
+        };
 
-            let mode = if is_directory_mode {
-                RunMode::Directory
-            } else {
-                RunMode::File
-            };
+        // Calculate run mode
+        let run_mode = {
+            let mode = RunMode::values()
+                .into_iter()
+                .find(|m| s.starts_with(&m.syntax()))
+                .ok_or(CommandErr::MissingRunMode)?;
 
-            let s = s
-                .to_string()
-                .replace(&file_mode, "")
-                .replace(&directory_mode, "")
-                .trim()
-                .to_string();
+            *s = s.replacen(&mode.syntax(), "", 1).trim().to_string();
 
-            (mode, s)
+            mode
         };
 
-        if s.trim().is_empty() {
+        if s.is_empty() {
             return Err(CommandErr::MissingPathSpecification);
         }
 
-        // Make path
-        let (root_path, s) = {
-            let mut path = String::default();
+        // Make paths
+        let root_paths = {
+            let (root_paths, rest) = split_paths(s)?;
+            *s = rest;
+            root_paths
+        };
 
-            for c in s.chars() {
-                if c.is_whitespace() {
-                    break;
-                } else {
-                    path.push(c);
+        // Parse clauses
+        let mut chunk_size = None;
+        let mut cwd = None;
+        let mut env_file = None;
+        let mut environment = BTreeMap::new();
+        let mut filters = vec![];
+        let mut parallelism = 1;
+        let mut startup_mode = StartupMode::default();
+        let mut stdin_format = None;
+        let mut walk_options = WalkOptions::default();
+        loop {
+            let (token, rest) = split_token(s);
+            match Keyword::parse(&token) {
+                Some(Keyword::Chunk) => {
+                    let (value, rest) = split_token(&rest);
+                    chunk_size = match value.parse() {
+                        Ok(size) if size > 0 => Some(size),
+                        _ => return Err(CommandErr::InvalidChunkSize),
+                    };
+                    *s = rest;
                 }
-            }
-
-            let s = s.replace(&path, "").trim().to_string();
+                Some(Keyword::Cwd) => {
+                    let (value, rest) = split_token(&rest);
+                    if value.is_empty() || Keyword::parse(&value).is_some() {
+                        return Err(CommandErr::InvalidCwd);
+                    }
+                    cwd = Some(value);
+                    *s = rest;
+                }
+                Some(Keyword::EnvFile) => {
+                    let (value, rest) = split_token(&rest);
+                    if value.is_empty() || Keyword::parse(&value).is_some() {
+                        return Err(CommandErr::InvalidEnvFile);
+                    }
+                    env_file = Some(value);
+                    *s = rest;
+                }
+                Some(Keyword::Follow) => {
+                    walk_options.follow_links = true;
+                    *s = rest;
+                }
+                Some(Keyword::MaxDepth) => {
+                    let (value, rest) = split_token(&rest);
+                    walk_options.max_depth =
+                        Some(value.parse().map_err(|_| CommandErr::InvalidMaxDepth)?);
+                    *s = rest;
+                }
+                Some(Keyword::MinDepth) => {
+                    let (value, rest) = split_token(&rest);
+                    walk_options.min_depth =
+                        Some(value.parse().map_err(|_| CommandErr::InvalidMinDepth)?);
+                    *s = rest;
+                }
+                Some(Keyword::NoHidden) => {
+                    walk_options.skip_hidden = true;
+                    *s = rest;
+                }
+                Some(Keyword::Parallel) => {
+                    // The number of jobs is optional and defaults to the CPU count.
+                    let (value, value_rest) = split_token(&rest);
+                    *s = match value.parse::<usize>() {
+                        Ok(0) => return Err(CommandErr::InvalidParallelism),
+                        Ok(jobs) => {
+                            parallelism = jobs;
+                            value_rest
+                        }
+                        Err(_) if value.starts_with('-') => {
+                            return Err(CommandErr::InvalidParallelism)
+                        }
+                        Err(_) => {
+                            parallelism = thread::available_parallelism().map_or(1, |n| n.get());
+                            rest
+                        }
+                    };
+                }
+                Some(Keyword::Startup) => {
+                    let (value, rest) = split_token(&rest);
+                    startup_mode =
+                        StartupMode::parse(&value).ok_or(CommandErr::InvalidStartupMode)?;
+                    *s = rest;
+                }
+                Some(Keyword::Stdin) => {
+                    let (value, rest) = split_token(&rest);
+                    stdin_format =
+                        Some(StdinFormat::parse(&value).ok_or(CommandErr::InvalidStdinFormat)?);
+                    *s = rest;
+                }
+                Some(Keyword::Where) => {
+                    let (option, rest) = split_token(&rest);
+                    let option = ScriptOptions::filters()
+                        .into_iter()
+                        .find(|o| o.pretty_print() == option)
+                        .ok_or(CommandErr::InvalidWhere)?;
+
+                    let (keyword, rest) = split_token(&rest);
+                    if Keyword::parse(&keyword) != Some(Keyword::In) {
+                        return Err(CommandErr::InvalidWhere);
+                    }
 
-            (path, s)
-        };
+                    // The values are either a bracketed list or a single value.
+                    let (values, rest) = match rest.starts_with('[') {
+                        true => split_list(&rest).ok_or(CommandErr::InvalidWhere)?,
+                        false => {
+                            let (value, rest) = split_token(&rest);
+                            (vec![value], rest)
+                        }
+                    };
+                    if values.is_empty()
+                        || values
+                            .iter()
+                            .any(|v| v.is_empty() || Keyword::parse(v).is_some())
+                    {
+                        return Err(CommandErr::InvalidWhere);
+                    }
 
-        // Make do
-        let s = {
-            let keyword = Keyword::Do.pretty_print();
-            if !s.starts_with(&keyword) {
-                return Err(CommandErr::MissingKeywordDo);
-            }
+                    filters.push((option, values));
+                    *s = rest;
+                }
+                Some(Keyword::With) => {
+                    let (value, mut rest) = split_token(&rest);
+                    if Keyword::parse(&value) != Some(Keyword::Env) {
+                        return Err(CommandErr::InvalidEnvironment);
+                    }
 
-            s.replace(&keyword, "").trim().to_string()
-        };
+                    let mut found = false;
+                    loop {
+                        let (pair, pair_rest) = split_token(&rest);
+                        match pair.split_once('=') {
+                            Some((key, value)) if !key.is_empty() => {
+                                environment.insert(key.to_string(), value.to_string());
+                                found = true;
+                                rest = pair_rest;
+                            }
+                            _ => break,
+                        }
+                    }
 
-        // Parse command
-        let (command, s) = {
-            let mut command = String::default();
-            let end = Keyword::End.pretty_print();
-            let mut found_end = s.starts_with(&end);
-            let mut s = s;
-
-            if !found_end {
-                while s.len() > 0 {
-                    let c = s.remove(0);
-
-                    if s.starts_with(&end) && c.is_whitespace() {
-                        found_end = true;
-                        s = s.replacen(&end, "", 1);
-                        break;
-                    } else {
-                        command.push(c);
+                    if !found {
+                        return Err(CommandErr::InvalidEnvironment);
                     }
+                    *s = rest;
                 }
+                _ => break,
             }
+        }
 
-            if !found_end {
-                return Err(CommandErr::MissingKeywordEnd);
+        // Parse steps
+        let steps = {
+            let (steps, rest) = split_block(s)?;
+            *s = rest;
+            steps
+        };
+
+        // Parse handlers
+        let mut on_failure = vec![];
+        let mut on_success = vec![];
+        loop {
+            let (token, rest) = split_token(s);
+            if Keyword::parse(&token) != Some(Keyword::On) {
+                break;
             }
 
-            (command, s)
-        };
+            let (value, rest) = split_token(&rest);
+            let outcome = Outcome::parse(&value).ok_or(CommandErr::InvalidHandler)?;
+            let (handler, rest) = split_block(&rest)?;
+            match outcome {
+                Outcome::Failure => on_failure = handler,
+                Outcome::Success => on_success = handler,
+            }
+            *s = rest;
+        }
 
-        // TODO: where clauses
+        // Anything left over would otherwise be silently dropped, such as a step containing `end`.
+        if !s.is_empty() {
+            return Err(CommandErr::UnexpectedInput);
+        }
 
         // TODO: ensure only whitelisted values are present
 
         Ok(Self {
-            command,
-            root_path,
+            chunk_size,
+            cwd,
+            dependencies,
+            dry_run: false,
+            env_file,
+            environment,
+            filters,
+            name,
+            on_failure,
+            on_success,
+            parallelism,
+            root_paths,
             run_mode,
+            startup_mode,
+            stdin_format,
+            steps,
+            walk_options,
         })
     }
 
@@ -137,64 +329,789 @@ impl Command {
                 .collect::<Vec<String>>()
                 .join("|")
         );
+        let ex3 = format!(
+            "foreach dir in ./crates do echo {options} end",
+            options = RunMode::EachDirectory
+                .allowed_options()
+                .iter()
+                .map(|m| m.pretty_print())
+                .collect::<Vec<String>>()
+                .join("|")
+        );
+
+        vec![ex1, ex2, ex3]
+    }
+
+    /// Returns the environment variables for the child process. How the files changed is exported
+    /// as `WITD_EVENT` and the script options for the trigger with a `WITD_` prefix, followed by the
+    /// env file and those set with `with env`.
+    /// The env file is read on every execution so changes to it are always picked up.
+    fn environment(
+        &self,
+        trigger: Trigger,
+        change: Option<Change>,
+    ) -> Result<Vec<(String, String)>, EnvFileErr> {
+        let cwd = self.working_directory(trigger);
+        let mut environment = vec![];
+        if let Some(change) = change {
+            environment.push(("WITD_EVENT".to_string(), change.pretty_print()));
+        }
 
-        vec![ex1, ex2]
+        for script_option in ScriptOptions::values() {
+            if let Some(value) = self.script_value(script_option, trigger, cwd.as_deref()) {
+                environment.push((format!("WITD_{}", script_option.pretty_print()), value));
+            }
+        }
+
+        if let Some(path) = &self.env_file {
+            environment.extend(env_file::load(path)?);
+        }
+
+        environment.extend(self.environment.clone());
+        Ok(environment)
     }
 
-    /// Returns a stringified version of the command to execute.
-    fn execution(&self, file: Option<&File>) -> String {
-        let mut command = self.command.clone();
+    /// Returns a stringified version of the step to execute.
+    fn execution(&self, step: &str, trigger: Trigger) -> String {
+        self.interpolate(step, trigger, self.working_directory(trigger).as_deref())
+    }
 
-        if let Some(file) = file {
-            for script_option in ScriptOptions::values() {
-                let identifier = script_option.pretty_print();
-                let value = match script_option {
-                    ScriptOptions::Directory => &file.directory,
-                    ScriptOptions::Ext => &file.extension,
-                    ScriptOptions::Name => &file.name,
-                    ScriptOptions::Path => &file.path,
-                };
+    /// Replaces the script options in the string with their values for the trigger.
+    /// Paths are rewritten relative to the working directory, if given.
+    fn interpolate(&self, s: &str, trigger: Trigger, cwd: Option<&str>) -> String {
+        let values: Vec<(String, String)> = ScriptOptions::values()
+            .into_iter()
+            .filter_map(|o| {
+                self.script_value(o, trigger, cwd)
+                    .map(|value| (o.pretty_print(), value))
+            })
+            .collect();
+
+        replace_identifiers(s, &values)
+    }
 
-                command = command.replace(&identifier, value);
+    /// Returns the value of the script option for the trigger, if it has one.
+    /// `DIR` and `ROOT` fall back to the root paths when there is no file to take them from.
+    /// Paths are rewritten relative to the working directory, if given.
+    fn script_value(
+        &self,
+        script_option: ScriptOptions,
+        trigger: Trigger,
+        cwd: Option<&str>,
+    ) -> Option<String> {
+        let path = |p: &str| match cwd {
+            Some(cwd) => relative_to(p, cwd),
+            None => p.to_string(),
+        };
+        let roots = self
+            .root_paths
+            .iter()
+            .map(|r| path(r))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        match (trigger, script_option) {
+            (Trigger::File(file), ScriptOptions::Directory) => Some(path(&file.directory)),
+            (Trigger::File(file), ScriptOptions::Ext) => Some(file.extension.clone()),
+            (Trigger::File(file), ScriptOptions::Name) => Some(file.name.clone()),
+            (Trigger::File(file), ScriptOptions::Path) => Some(path(&file.path)),
+            (Trigger::File(file), ScriptOptions::RelPath) => Some(file.relative_path()),
+            (Trigger::File(file), ScriptOptions::Root) => Some(path(&file.root)),
+            (Trigger::File(file), ScriptOptions::Target) => Some(path(&file.target)),
+            (Trigger::Directory(directory, _), ScriptOptions::Directory) => Some(path(directory)),
+            (Trigger::Directory(_, files), ScriptOptions::Root) => {
+                Some(files.first().map_or(roots, |f| path(&f.root)))
             }
+            (Trigger::Root(_), ScriptOptions::Directory | ScriptOptions::Root) => Some(roots),
+            _ => None,
         }
+    }
 
-        command = command.replace(&ScriptOptions::Directory.pretty_print(), &self.root_path);
+    /// Returns the paths of the changed files for the trigger, relative to the working directory.
+    fn trigger_paths(&self, trigger: Trigger) -> Vec<String> {
+        let cwd = self.working_directory(trigger);
+        trigger
+            .files()
+            .iter()
+            .map(|f| match &cwd {
+                Some(cwd) => relative_to(&f.path, cwd),
+                None => f.path.clone(),
+            })
+            .collect()
+    }
 
-        command
+    /// Returns the working directory for the trigger, if the command has one.
+    /// Script options such as `ROOT` may be used, e.g. `cwd ROOT` runs in the watched root.
+    fn working_directory(&self, trigger: Trigger) -> Option<String> {
+        self.cwd
+            .as_ref()
+            .map(|cwd| self.interpolate(cwd, trigger, None))
     }
 
-    /// Executes the given command on the given file.
-    pub fn execute(&self, file: Option<&File>) {
+    /// Returns the argv for each invocation of the step, along with the paths it is for.
+    /// A standalone `FILES` token expands to every changed path, split into chunks if a chunk size is set.
+    /// Paths written to stdin are chunked the same way, so each invocation only receives its own.
+    fn invocations(
+        &self,
+        step: &str,
+        trigger: Trigger,
+        with_stdin: bool,
+    ) -> Vec<(Vec<String>, Vec<String>)> {
         // rather hacky, but this will build up a command to execute by splitting off the tokens and the like.
-        let cmd = self.execution(file);
+        let tokens: Vec<String> = self
+            .execution(step, trigger)
+            .split_ascii_whitespace()
+            .map(String::from)
+            .collect();
+
+        if tokens.is_empty() {
+            return vec![];
+        }
+
+        let paths = self.trigger_paths(trigger);
+        let files = ScriptOptions::Files.pretty_print();
+        let is_stdin = with_stdin && self.stdin_format.is_some();
+        if !tokens.contains(&files) && (!is_stdin || paths.is_empty()) {
+            return vec![(tokens, paths)];
+        }
+
+        let chunk_size = self.chunk_size.unwrap_or(paths.len()).max(1);
+
+        paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let mut argv = vec![];
+                for token in tokens.iter() {
+                    if *token == files {
+                        argv.extend(chunk.iter().cloned());
+                    } else {
+                        argv.push(token.clone());
+                    }
+                }
+                (argv, chunk.to_vec())
+            })
+            .collect()
+    }
+
+    /// Returns the bytes to write to the command's stdin for the paths, if a stdin format was specified.
+    fn stdin(&self, paths: &[String]) -> Option<Vec<u8>> {
+        let format = self.stdin_format?;
+
+        let mut bytes = vec![];
+        for path in paths {
+            bytes.extend(path.as_bytes());
+            bytes.push(format.separator());
+        }
+
+        Some(bytes)
+    }
+
+    /// Executes each step of the command for the given trigger, stopping at the first step that fails,
+    /// then executes the handler for the outcome. Returns whether every step succeeded.
+    pub fn execute(
+        &self,
+        trigger: Trigger,
+        change: Option<Change>,
+        executor: &dyn Executor,
+        reporter: &dyn Reporter,
+    ) -> bool {
+        if self.dry_run {
+            self.report_invocations(trigger, reporter);
+            return true;
+        }
+
+        let environment = match self.environment(trigger, change) {
+            Ok(environment) => environment,
+            Err(e) => {
+                reporter.report(Event::Error {
+                    task: Some(self.label()),
+                    message: e.pretty_print(),
+                });
+                return false;
+            }
+        };
+
+        let exit_code =
+            self.execute_steps(&self.steps, trigger, true, &environment, executor, reporter);
+
+        let outcome = if exit_code == 0 {
+            Outcome::Success
+        } else {
+            Outcome::Failure
+        };
+
+        let handler: Vec<String> = self
+            .handler(outcome)
+            .iter()
+            .map(|step| {
+                step.replace(
+                    &ScriptOptions::ExitCode.pretty_print(),
+                    &exit_code.to_string(),
+                )
+            })
+            .collect();
+        if self.execute_steps(&handler, trigger, false, &environment, executor, reporter) != 0 {
+            reporter.report(Event::Error {
+                task: Some(self.label()),
+                message: format!(
+                    "The '{} {}' handler failed.",
+                    Keyword::On.pretty_print(),
+                    outcome.pretty_print()
+                ),
+            });
+        }
+
+        exit_code == 0
+    }
+
+    /// Reports the argv of every invocation for the trigger, exactly as it would be passed.
+    fn report_invocations(&self, trigger: Trigger, reporter: &dyn Reporter) {
+        for step in self.steps.iter() {
+            for (argv, _) in self.invocations(step, trigger, true) {
+                reporter.report(Event::DryRun {
+                    task: self.label(),
+                    argv,
+                    cwd: self.working_directory(trigger),
+                });
+            }
+        }
+    }
+
+    /// Executes the steps in order for the given trigger, stopping at the first step that fails.
+    /// Returns the exit code of the failing step, or zero if every step succeeded.
+    /// Handlers are executed without stdin, so `with_stdin` is only set for the steps.
+    fn execute_steps(
+        &self,
+        steps: &[String],
+        trigger: Trigger,
+        with_stdin: bool,
+        environment: &[(String, String)],
+        executor: &dyn Executor,
+        reporter: &dyn Reporter,
+    ) -> i32 {
+        for (i, step) in steps.iter().enumerate() {
+            let exit_code =
+                self.execute_step(step, trigger, with_stdin, environment, executor, reporter);
+            if exit_code != 0 {
+                if steps.len() > 1 {
+                    reporter.report(Event::Error {
+                        task: Some(self.label()),
+                        message: format!(
+                            "Step {} of {} failed: '{}'",
+                            i + 1,
+                            steps.len(),
+                            self.execution(step, trigger)
+                        ),
+                    });
+                }
+                return exit_code;
+            }
+        }
+
+        0
+    }
+
+    /// Executes a single step for the given trigger.
+    /// Returns the exit code of the first invocation that failed, or zero if every invocation succeeded.
+    fn execute_step(
+        &self,
+        step: &str,
+        trigger: Trigger,
+        with_stdin: bool,
+        environment: &[(String, String)],
+        executor: &dyn Executor,
+        reporter: &dyn Reporter,
+    ) -> i32 {
+        let mut exit_code = 0;
+        let cwd = self.working_directory(trigger);
+
+        for (argv, paths) in self.invocations(step, trigger, with_stdin) {
+            let invocation = Invocation {
+                argv,
+                cwd: cwd.clone(),
+                environment: environment.to_vec(),
+                stdin: self.stdin(&paths).filter(|_| with_stdin),
+            };
+
+            let started = Instant::now();
+            let output = executor.execute(&invocation, &|pid| {
+                reporter.report(Event::Start {
+                    task: self.label(),
+                    pid,
+                    argv: invocation.argv.clone(),
+                    cwd: cwd.clone(),
+                })
+            });
+
+            let output = match output {
+                Ok(output) => output,
+                Err(e) => {
+                    reporter.report(Event::Error {
+                        task: Some(self.label()),
+                        message: format!("Unable to execute '{}': {}", invocation.argv[0], e),
+                    });
+                    // Mirror the shell's exit code for a command that could not be run.
+                    if exit_code == 0 {
+                        exit_code = 127;
+                    }
+                    continue;
+                }
+            };
+
+            for (stream, bytes) in [
+                (Stream::Stdout, &output.stdout),
+                (Stream::Stderr, &output.stderr),
+            ] {
+                let lines: Vec<String> = String::from_utf8_lossy(bytes)
+                    .lines()
+                    .map(String::from)
+                    .collect();
+                if !lines.is_empty() {
+                    reporter.report(Event::Output {
+                        task: self.label(),
+                        pid: output.pid,
+                        stream,
+                        lines,
+                    });
+                }
+            }
+
+            reporter.report(Event::Exit {
+                task: self.label(),
+                pid: output.pid,
+                code: output.code,
+                duration: started.elapsed(),
+            });
+
+            if exit_code == 0 {
+                exit_code = output.code;
+            }
+        }
+
+        exit_code
+    }
+
+    /// Runs the given job, executing the command once per trigger for the job's run mode.
+    /// Returns whether every execution succeeded.
+    pub fn run(&self, job: &Job, executor: &dyn Executor, reporter: &dyn Reporter) -> bool {
+        match job.run_mode {
+            RunMode::Directory => {
+                let trigger = Trigger::Root(&job.files);
+                self.execute(trigger, job.change(&job.files), executor, reporter)
+            }
+            RunMode::EachDirectory => {
+                let mut directories: BTreeMap<&str, Vec<File>> = BTreeMap::new();
+                for file in job.files.iter() {
+                    directories
+                        .entry(&file.directory)
+                        .or_default()
+                        .push(file.clone());
+                }
+
+                let triggers: Vec<Trigger> = directories
+                    .iter()
+                    .map(|(directory, files)| Trigger::Directory(directory, files))
+                    .collect();
+                self.execute_triggers(&triggers, job, executor, reporter)
+            }
+            RunMode::File => {
+                let triggers: Vec<Trigger> = job.files.iter().map(Trigger::File).collect();
+                self.execute_triggers(&triggers, job, executor, reporter)
+            }
+        }
+    }
+
+    /// Executes the command for each trigger of the job, running up to the command's parallelism at once.
+    /// Output is buffered per job, so jobs never interleave their output.
+    fn execute_triggers(
+        &self,
+        triggers: &[Trigger],
+        job: &Job,
+        executor: &dyn Executor,
+        reporter: &dyn Reporter,
+    ) -> bool {
+        let jobs = self.parallelism().min(triggers.len());
+        if jobs <= 1 {
+            let mut succeeded = true;
+            for trigger in triggers {
+                succeeded &=
+                    self.execute(*trigger, job.change(trigger.files()), executor, reporter);
+            }
+            return succeeded;
+        }
 
-        let mut tokens = vec![];
-        for token in cmd.split_ascii_whitespace() {
-            tokens.push(token.to_string());
+        let next = AtomicUsize::new(0);
+        let succeeded = AtomicBool::new(true);
+        thread::scope(|scope| {
+            for _ in 0..jobs {
+                scope.spawn(|| {
+                    while let Some(trigger) = triggers.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        if !self.execute(*trigger, job.change(trigger.files()), executor, reporter)
+                        {
+                            succeeded.store(false, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+
+        succeeded.into_inner()
+    }
+
+    /// Sets the command to print what would be executed rather than executing it.
+    pub fn with_dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Sets the env file to load variables from, unless one was already specified.
+    pub fn with_default_env_file(mut self, path: String) -> Self {
+        self.env_file.get_or_insert(path);
+        self
+    }
+
+    /// Returns the names of the tasks this command depends on.
+    pub fn dependencies(&self) -> &[String] {
+        &self.dependencies
+    }
+
+    /// Returns the handler steps to execute for the given outcome.
+    pub fn handler(&self, outcome: Outcome) -> &[String] {
+        match outcome {
+            Outcome::Failure => &self.on_failure,
+            Outcome::Success => &self.on_success,
+        }
+    }
+
+    /// Returns a key identifying the command, stable across sessions, used to persist its state.
+    pub fn key(&self) -> String {
+        // FNV-1a, since the std hasher is not guaranteed to be stable between releases.
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in self.serialize().bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+
+        format!("{:016x}", hash)
+    }
+
+    /// Returns a short label for the command: its task name, or its run mode and root paths.
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("{} {}", self.run_mode.syntax(), self.root_paths.join(" ")),
         }
+    }
 
-        let cmd = tokens[0].clone();
-        let args: Vec<String> = tokens.iter().skip(1).map(|s| s.clone()).collect();
+    /// Returns whether the file has one of the allowed values for every `where` clause.
+    pub fn matches(&self, file: &File) -> bool {
+        self.filters.iter().all(|(option, values)| {
+            self.script_value(*option, Trigger::File(file), None)
+                .is_some_and(|value| values.contains(&value))
+        })
+    }
 
-        let output = process::Command::new(cmd)
-            .args(args)
-            .output()
-            .expect("failed to execute process");
+    /// Returns the task name for the command, if it is a task.
+    pub fn name(&self) -> Option<&String> {
+        self.name.as_ref()
+    }
 
-        let output = String::from_utf8(output.stdout).unwrap();
-        println!("{}", output);
+    /// Returns the maximum number of jobs that may run at once.
+    pub fn parallelism(&self) -> usize {
+        self.parallelism
     }
 
-    /// Returns the root path for the command.
-    pub fn root_path(&self) -> &String {
-        &self.root_path
+    /// Returns the root paths for the command.
+    pub fn root_paths(&self) -> &[String] {
+        &self.root_paths
     }
 
     /// Returns the run mode for the command.
     pub fn run_mode(&self) -> RunMode {
         self.run_mode
     }
+
+    /// Returns every field identifying the command, one per line, to be hashed into its key.
+    /// Each field is written out explicitly so the key never changes with how its types are
+    /// debug printed. Whether it is a dry run does not identify the command.
+    fn serialize(&self) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "-".into());
+        let list = |values: &[String]| values.join("\0");
+        let environment: Vec<String> = self
+            .environment
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        let filters: Vec<String> = self
+            .filters
+            .iter()
+            .map(|(option, values)| format!("{}={}", option.pretty_print(), values.join(",")))
+            .collect();
+
+        [
+            format!("chunk {}", optional(self.chunk_size.map(|c| c.to_string()))),
+            format!("cwd {}", optional(self.cwd.clone())),
+            format!("dependencies {}", list(&self.dependencies)),
+            format!("envfile {}", optional(self.env_file.clone())),
+            format!("environment {}", list(&environment)),
+            format!("filters {}", list(&filters)),
+            format!("follow {}", self.walk_options.follow_links),
+            format!(
+                "maxdepth {}",
+                optional(self.walk_options.max_depth.map(|d| d.to_string()))
+            ),
+            format!(
+                "mindepth {}",
+                optional(self.walk_options.min_depth.map(|d| d.to_string()))
+            ),
+            format!("mode {}", self.run_mode.syntax()),
+            format!("name {}", optional(self.name.clone())),
+            format!("nohidden {}", self.walk_options.skip_hidden),
+            format!("on failure {}", list(&self.on_failure)),
+            format!("on success {}", list(&self.on_success)),
+            format!("parallel {}", self.parallelism),
+            format!("paths {}", list(&self.root_paths)),
+            format!("startup {}", self.startup_mode.pretty_print()),
+            format!(
+                "stdin {}",
+                optional(self.stdin_format.map(|f| f.pretty_print()))
+            ),
+            format!("steps {}", list(&self.steps)),
+        ]
+        .join("\n")
+    }
+
+    /// Returns the startup mode for the command.
+    pub fn startup_mode(&self) -> StartupMode {
+        self.startup_mode
+    }
+
+    /// Returns the options for traversing the root path.
+    pub fn walk_options(&self) -> &WalkOptions {
+        &self.walk_options
+    }
+}
+
+/// Parses a task header such as `build` or `test after build, lint`, returning the name and dependencies.
+fn parse_task_header(header: &str) -> Result<(String, Vec<String>), CommandErr> {
+    let mut tokens = header
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|t| !t.is_empty());
+
+    let name = match tokens.next() {
+        Some(name) if Keyword::parse(name).is_none() => name.to_string(),
+        _ => return Err(CommandErr::InvalidTaskHeader),
+    };
+
+    match tokens.next() {
+        None => Ok((name, vec![])),
+        Some(token) if Keyword::parse(token) == Some(Keyword::After) => {
+            let dependencies: Vec<String> = tokens.map(String::from).collect();
+            if dependencies.is_empty() {
+                return Err(CommandErr::InvalidTaskHeader);
+            }
+
+            Ok((name, dependencies))
+        }
+        Some(_) => Err(CommandErr::InvalidTaskHeader),
+    }
+}
+
+/// Splits off the root paths, either a bracketed list such as `[./src, ./tests]` or the
+/// whitespace delimited paths preceding the first keyword.
+fn split_paths(s: &str) -> Result<(Vec<String>, String), CommandErr> {
+    let s = s.trim_start();
+    let (paths, rest) = match s.starts_with('[') {
+        true => split_list(s).ok_or(CommandErr::MissingPathListEnd)?,
+        false => {
+            let mut paths = vec![];
+            let mut s = s.to_string();
+            loop {
+                let (token, rest) = split_token(&s);
+                if token.is_empty() || Keyword::parse(&token).is_some() {
+                    break;
+                }
+
+                paths.push(token);
+                s = rest;
+            }
+
+            (paths, s)
+        }
+    };
+
+    if paths.is_empty() {
+        return Err(CommandErr::MissingPathSpecification);
+    }
+
+    Ok((paths, rest))
+}
+
+/// Splits off a bracketed list such as `[a, b]`, returning its items and the trimmed remainder.
+/// Returns `None` if the list is never closed.
+fn split_list(s: &str) -> Option<(Vec<String>, String)> {
+    let list = s.trim_start().strip_prefix('[')?;
+    let end = list.find(']')?;
+    let items = list[..end]
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|i| !i.is_empty())
+        .map(String::from)
+        .collect();
+
+    Some((items, list[end + 1..].trim().to_string()))
+}
+
+/// Rewrites the path relative to the base directory, both being relative to the current directory.
+/// Absolute paths are left as is.
+fn relative_to(path: &str, base: &str) -> String {
+    /// Resolves `.` and `..` components without touching the filesystem.
+    fn normalize(path: &Path) -> Vec<Component<'_>> {
+        let mut components = vec![];
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir if matches!(components.last(), Some(Component::Normal(_))) => {
+                    components.pop();
+                }
+                component => components.push(component),
+            }
+        }
+        components
+    }
+
+    if Path::new(path).is_absolute() {
+        return path.to_string();
+    }
+
+    let current = env::current_dir().unwrap_or_default();
+    let path = current.join(path);
+    let base = current.join(base);
+    let path = normalize(&path);
+    let base = normalize(&base);
+
+    let common = path
+        .iter()
+        .zip(base.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push(Component::ParentDir);
+    }
+    for component in &path[common..] {
+        relative.push(component);
+    }
+
+    if relative.as_os_str().is_empty() {
+        ".".into()
+    } else {
+        relative.display().to_string()
+    }
+}
+
+/// Replaces each identifier in the string with its value. Identifiers only match whole words, so
+/// `WITD_PATH` is left as is, and values are never replaced again, so a path containing `NAME` is kept.
+fn replace_identifiers(s: &str, values: &[(String, String)]) -> String {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut replaced = String::new();
+    let mut previous = None;
+    let mut rest = s;
+
+    while let Some(c) = rest.chars().next() {
+        let value = match previous.is_some_and(is_word) {
+            true => None,
+            false => values.iter().find(|(identifier, _)| {
+                rest.strip_prefix(identifier.as_str())
+                    .is_some_and(|r| !r.starts_with(is_word))
+            }),
+        };
+
+        match value {
+            Some((identifier, value)) => {
+                replaced.push_str(value);
+                previous = identifier.chars().last();
+                rest = &rest[identifier.len()..];
+            }
+            None => {
+                replaced.push(c);
+                previous = Some(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    replaced
+}
+
+/// Splits off a `do ... end` block, returning its steps and the trimmed remainder.
+/// The block ends at the first `end`; a literal `end` within a step is escaped as `\end`.
+fn split_block(s: &str) -> Result<(Vec<String>, String), CommandErr> {
+    let (token, mut s) = split_token(s);
+    if Keyword::parse(&token) != Some(Keyword::Do) {
+        return Err(CommandErr::MissingKeywordDo);
+    }
+
+    let mut body = vec![];
+    loop {
+        let (token, rest) = split_token(&s);
+        match Keyword::parse(&token) {
+            Some(Keyword::End) => return Ok((split_steps(&body.join(" "))?, rest)),
+            _ if token.is_empty() => return Err(CommandErr::MissingKeywordEnd),
+            _ => body.push(token),
+        }
+        s = rest;
+    }
+}
+
+/// Splits the command into the steps separated by `then`.
+/// A literal `then` within a step is escaped as `\then`.
+fn split_steps(command: &str) -> Result<Vec<String>, CommandErr> {
+    let then = Keyword::Then.pretty_print();
+    let mut steps = vec![String::default()];
+
+    for token in command.split_ascii_whitespace() {
+        if token == then {
+            steps.push(String::default());
+            continue;
+        }
+
+        let step = steps.last_mut().unwrap_or_else(|| unreachable!());
+        if !step.is_empty() {
+            step.push(' ');
+        }
+        step.push_str(unescape_keyword(token));
+    }
+
+    match steps.as_slice() {
+        [step] if step.is_empty() => Ok(vec![]),
+        _ if steps.iter().any(String::is_empty) => Err(CommandErr::EmptyStep),
+        _ => Ok(steps),
+    }
+}
+
+/// Removes the backslash escaping a literal `end` or `then` within a step, so `\end` is `end`
+/// and `\\end` is `\end`. Any other token is left as is.
+fn unescape_keyword(token: &str) -> &str {
+    match token.strip_prefix('\\') {
+        Some(rest)
+            if [Keyword::End, Keyword::Then]
+                .iter()
+                .any(|k| rest.trim_start_matches('\\') == k.pretty_print()) =>
+        {
+            rest
+        }
+        _ => token,
+    }
+}
+
+/// Splits off the first whitespace delimited token, returning it and the trimmed remainder.
+fn split_token(s: &str) -> (String, String) {
+    let s = s.trim_start();
+    match s.find(char::is_whitespace) {
+        Some(i) => (s[..i].to_string(), s[i..].trim().to_string()),
+        None => (s.to_string(), String::new()),
+    }
 }
 
 #[cfg(test)]
@@ -202,123 +1119,498 @@ mod tests {
     use std::time::Duration;
 
     use super::*;
+    use crate::witd::{Format, RecordingExecutor, StreamReporter};
 
     fn file() -> File {
         File {
-            created_at: Duration::from_millis(333),
+            created_at: Some(Duration::from_millis(333)),
             directory: "./testy".into(),
             extension: "obj".into(),
             modified_at: Duration::from_millis(444),
             name: "testy-mctest.obj".into(),
             path: "./testy/testy-mctest.obj".into(),
+            root: "./testy".into(),
+            target: "/home/testy/testy-mctest.obj".into(),
         }
     }
 
+    /// A reporter whose output is discarded.
+    fn reporter() -> StreamReporter {
+        StreamReporter::new(Format::Text, Box::new(std::io::sink()))
+    }
+
     fn cmd() -> Command {
         Command {
-            command: "echo NAME".into(),
-            root_path: "testy/test/src".into(),
+            chunk_size: None,
+            cwd: None,
+            dependencies: vec![],
+            dry_run: false,
+            env_file: None,
+            environment: BTreeMap::new(),
+            filters: vec![],
+            name: None,
+            on_failure: vec![],
+            on_success: vec![],
+            parallelism: 1,
+            root_paths: vec!["testy/test/src".into()],
             run_mode: RunMode::File,
+            startup_mode: StartupMode::All,
+            stdin_format: None,
+            steps: vec!["echo NAME".into()],
+            walk_options: WalkOptions::default(),
         }
     }
 
     describe!(examples => {
         #[test]
         fn examples() {
-            assert_eq!(vec!["directory ./src do echo DIR end".to_string(), "foreach file in ./src do echo DIR|EXT|NAME|PATH end".to_string()], Command::examples());
+            assert_eq!(vec!["directory ./src do echo DIR|FILES|ROOT end".to_string(), "foreach file in ./src do echo DIR|EXT|FILES|NAME|PATH|RELPATH|ROOT|TARGET end".to_string(), "foreach dir in ./crates do echo DIR|FILES|ROOT end".to_string()], Command::examples());
         }
     });
 
-    describe!(execution => {
-       #[test]
-        fn dir_replaces_filename(){
-            let mut command = cmd();
-            command.command = "echo DIR".into();
-
-            let expected = format!("echo {}", file().directory);
-            assert_eq!(expected, command.execution(Some(&file())));
+    describe!(execute => {
+        /// Returns the argv of every invocation the executor recorded, joined by spaces.
+        fn argvs(executor: &RecordingExecutor) -> Vec<String> {
+            executor.invocations().into_iter().map(|i| i.argv.join(" ")).collect()
         }
 
-          #[test]
-        fn dir_replaces_filename_if_no_file(){
-            let mut command = cmd();
-            command.command = "echo DIR".into();
+        #[test]
+        fn runs_every_step(){
+            let command = Command{ steps: vec!["true".into(), "echo".into()], ..cmd() };
+            let executor = RecordingExecutor::new();
 
-            let expected = format!("echo {}", cmd().root_path());
-            assert_eq!(expected, command.execution(None));
+            assert!(command.execute(Trigger::Root(&[]), None, &executor, &reporter()));
+            assert_eq!(vec!["true", "echo"], argvs(&executor));
         }
 
         #[test]
-        fn name_replaces_filename(){
-            let mut command = cmd();
-            command.command = "echo NAME".into();
+        fn stops_at_first_failing_step(){
+            let command = Command{ steps: vec!["false".into(), "echo".into()], ..cmd() };
+            let executor = RecordingExecutor::new().with_exit_code("false", 1);
 
-            let expected = format!("echo {}", file().name);
-            assert_eq!(expected, command.execution(Some(&file())));
+            assert!(!command.execute(Trigger::Root(&[]), None, &executor, &reporter()));
+            assert_eq!(vec!["false"], argvs(&executor));
         }
 
         #[test]
-        fn path_replaces_path(){
-            let command = Command{ command: "echo PATH".into(), root_path: "".into(), run_mode: RunMode::File };
+        fn dry_run_does_not_execute(){
+            let command = Command{ steps: vec!["false".into(), "echo".into()], ..cmd() }.with_dry_run();
+            let executor = RecordingExecutor::new().with_exit_code("false", 1);
 
-            let expected = format!("echo {}", file().path);
-            assert_eq!(expected, command.execution(Some(&file())));
+            assert!(command.execute(Trigger::Root(&[]), None, &executor, &reporter()));
+            assert!(argvs(&executor).is_empty());
         }
 
         #[test]
-        fn ext_replaces_ext(){
-            let command = Command{ command: "echo EXT".into(), root_path: "".into(), run_mode: RunMode::File };
+        fn failure_handler_receives_exit_code(){
+            let on_failure = vec!["echo failed EXIT_CODE".into()];
+            let command = Command{ steps: vec!["false".into()], on_failure, ..cmd() };
+            let executor = RecordingExecutor::new().with_exit_code("false", 3);
 
-            let expected = format!("echo {}", file().extension);
-            assert_eq!(expected, command.execution(Some(&file())));
+            assert!(!command.execute(Trigger::Root(&[]), None, &executor, &reporter()));
+            assert_eq!(vec!["false", "echo failed 3"], argvs(&executor));
         }
 
         #[test]
-        fn complex(){
-            let command = Command{ command: "echo testy_NAME_path_PATH_ext_EXT".into(), root_path: "".into(), run_mode: RunMode::File };
-
-            let expected = format!("echo testy_{name}_path_{path}_ext_{ext}", name = file().name, path = file().path, ext = file().extension);
-            assert_eq!(expected, command.execution(Some(&file())));
+        fn success_handler_runs_on_success(){
+            let on_success = vec!["echo passed".into()];
+            let on_failure = vec!["echo failed".into()];
+            let command = Command{ steps: vec!["true".into()], on_failure, on_success, ..cmd() };
+            let executor = RecordingExecutor::new();
+
+            assert!(command.execute(Trigger::Root(&[]), None, &executor, &reporter()));
+            assert_eq!(vec!["true", "echo passed"], argvs(&executor));
         }
     });
 
-    describe!(parse => {
-        fn parse(input: &str) -> Result<Command, CommandErr>{
-            Command::parse(input)
+    describe!(environment => {
+        #[test]
+        fn file_exports_script_options(){
+            let command = Command{ environment: BTreeMap::from([("LOG".to_string(), "debug".to_string())]), ..cmd() };
+            let file = file();
+
+            let expected: Vec<(String, String)> = vec![
+                ("WITD_EVENT", "modified"),
+                ("WITD_DIR", file.directory.as_str()),
+                ("WITD_EXT", file.extension.as_str()),
+                ("WITD_NAME", file.name.as_str()),
+                ("WITD_PATH", file.path.as_str()),
+                ("WITD_RELPATH", "testy-mctest.obj"),
+                ("WITD_ROOT", file.root.as_str()),
+                ("WITD_TARGET", file.target.as_str()),
+                ("LOG", "debug"),
+            ].into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+            assert_eq!(Ok(expected), command.environment(Trigger::File(&file), Some(Change::Modified)));
         }
 
         #[test]
-        fn empty_input_returns_err(){
-            let input = " ";
-
-            let expected = Err(CommandErr::EmptyInput);
-            let actual = parse(input);
-
-            assert_eq!(expected, actual);
+        fn root_exports_roots(){
+            let command = Command{ root_paths: vec!["./src".into(), "./tests".into()], ..cmd() };
+
+            let expected: Vec<(String, String)> = vec![
+                ("WITD_DIR", "./src ./tests"),
+                ("WITD_ROOT", "./src ./tests"),
+            ].into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+            assert_eq!(Ok(expected), command.environment(Trigger::Root(&[]), None));
         }
 
         #[test]
-        fn missing_run_mode_returns_err(){
-            let input = " garbage day";
+        fn env_file_is_loaded_before_with_env(){
+            let path = std::env::temp_dir().join(format!("witd-env-file-{}", std::process::id()));
+            std::fs::write(&path, "LOG=info\nPORT=80\n").unwrap();
 
-            let expected = Err(CommandErr::MissingRunMode);
-            let actual = parse(input);
+            let env_file = Some(path.display().to_string());
+            let environment = BTreeMap::from([("LOG".to_string(), "debug".to_string())]);
+            let command = Command{ env_file, environment, ..cmd() };
+
+            let actual = command.environment(Trigger::Root(&[]), None).unwrap();
+            let _ = std::fs::remove_file(&path);
+
+            assert_eq!(&[("LOG".to_string(), "info".to_string()), ("PORT".to_string(), "80".to_string()), ("LOG".to_string(), "debug".to_string())], &actual[2..]);
+        }
+
+        #[test]
+        fn missing_env_file_returns_err(){
+            let command = Command{ env_file: Some("./does/not/exist/.env".into()), ..cmd() };
+            assert!(command.environment(Trigger::Root(&[]), None).is_err());
+        }
+    });
+
+    describe!(key => {
+        #[test]
+        fn same_command_returns_same_key(){
+            assert_eq!(cmd().key(), cmd().key());
+            assert_eq!(cmd().key(), cmd().with_dry_run().key());
+        }
+
+        #[test]
+        fn different_commands_return_different_keys(){
+            let command = Command{ steps: vec!["echo PATH".into()], ..cmd() };
+            assert_ne!(cmd().key(), command.key());
+        }
+
+        #[test]
+        fn key_does_not_change_between_versions(){
+            assert_eq!("b783d4eb9fbd9ac3", cmd().key());
+        }
+    });
+
+    describe!(label => {
+        #[test]
+        fn task_returns_name(){
+            let command = Command{ name: Some("build".into()), ..cmd() };
+            assert_eq!("build", command.label());
+        }
+
+        #[test]
+        fn command_returns_run_mode_and_roots(){
+            let command = Command{ root_paths: vec!["./src".into(), "./tests".into()], ..cmd() };
+            assert_eq!("foreach file in ./src ./tests", command.label());
+        }
+    });
+
+    describe!(matches => {
+        #[test]
+        fn without_filters_matches_every_file(){
+            assert!(cmd().matches(&file()));
+        }
+
+        #[test]
+        fn every_filter_must_match(){
+            let command = |filters: Vec<(ScriptOptions, Vec<String>)>| Command{ filters, ..cmd() };
+            let ext = (ScriptOptions::Ext, vec!["rs".into(), "obj".into()]);
+
+            assert!(command(vec![ext.clone()]).matches(&file()));
+            assert!(command(vec![ext.clone(), (ScriptOptions::Directory, vec!["./testy".into()])]).matches(&file()));
+            assert!(!command(vec![ext, (ScriptOptions::Directory, vec!["./src".into()])]).matches(&file()));
+        }
+    });
+
+    describe!(relative_to => {
+        #[test]
+        fn path_inside_base(){
+            assert_eq!("src/main.rs", relative_to("./frontend/src/main.rs", "./frontend"));
+        }
+
+        #[test]
+        fn path_equal_to_base(){
+            assert_eq!(".", relative_to("./frontend/", "frontend"));
+        }
+
+        #[test]
+        fn path_outside_base(){
+            assert_eq!("../backend/main.rs", relative_to("./backend/main.rs", "./frontend/"));
+        }
+
+        #[test]
+        fn absolute_path_is_unchanged(){
+            assert_eq!("/home/testy/a.rs", relative_to("/home/testy/a.rs", "./frontend"));
+        }
+    });
+
+    describe!(execution => {
+        #[test]
+        fn paths_are_relative_to_cwd(){
+            let command = Command{ steps: vec!["echo DIR PATH ROOT TARGET RELPATH".into()], cwd: Some("./testy".into()), ..cmd() };
+
+            let expected = "echo . testy-mctest.obj . /home/testy/testy-mctest.obj testy-mctest.obj";
+            assert_eq!(expected, command.execution(&command.steps[0], Trigger::File(&file())));
+        }
+
+        #[test]
+        fn identifiers_within_words_are_kept(){
+            let command = Command{ steps: vec!["printenv WITD_PATH PATH_1 NAMES PATH".into()], ..cmd() };
+
+            let expected = format!("printenv WITD_PATH PATH_1 NAMES {}", file().path);
+            assert_eq!(expected, command.execution(&command.steps[0], Trigger::File(&file())));
+        }
+
+        #[test]
+        fn values_are_not_replaced_again(){
+            let command = Command{ steps: vec!["echo PATH NAME".into()], ..cmd() };
+            let file = File{ path: "./NAME/a.rs".into(), name: "a.rs".into(), ..file() };
+
+            assert_eq!("echo ./NAME/a.rs a.rs", command.execution(&command.steps[0], Trigger::File(&file)));
+        }
+
+        #[test]
+        fn cwd_may_use_script_options(){
+            let command = Command{ cwd: Some("ROOT".into()), ..cmd() };
+            assert_eq!(Some("./testy".into()), command.working_directory(Trigger::File(&file())));
+        }
+
+       #[test]
+        fn dir_replaces_filename(){
+            let mut command = cmd();
+            command.steps = vec!["echo DIR".into()];
+
+            let expected = format!("echo {}", file().directory);
+            assert_eq!(expected, command.execution(&command.steps[0], Trigger::File(&file())));
+        }
+
+          #[test]
+        fn dir_replaces_filename_if_no_file(){
+            let mut command = cmd();
+            command.steps = vec!["echo DIR".into()];
+
+            let expected = format!("echo {}", cmd().root_paths().join(" "));
+            assert_eq!(expected, command.execution(&command.steps[0], Trigger::Root(&[])));
+        }
+
+        #[test]
+        fn dir_replaces_directory(){
+            let mut command = cmd();
+            command.steps = vec!["cargo build --manifest-path DIR/Cargo.toml".into()];
+
+            let expected = "cargo build --manifest-path ./crates/a/Cargo.toml";
+            assert_eq!(expected, command.execution(&command.steps[0], Trigger::Directory("./crates/a", &[])));
+        }
+
+        #[test]
+        fn root_and_relpath_replace_root_and_relative_path(){
+            let command = Command{ steps: vec!["cp RELPATH ROOT/PATH".into()], ..cmd() };
+
+            let expected = "cp testy-mctest.obj ./testy/./testy/testy-mctest.obj";
+            assert_eq!(expected, command.execution(&command.steps[0], Trigger::File(&file())));
+        }
+
+        #[test]
+        fn root_replaces_roots_if_no_file(){
+            let command = Command{ steps: vec!["ls ROOT".into()], root_paths: vec!["./src".into(), "./tests".into()], ..cmd() };
+
+            assert_eq!("ls ./src ./tests", command.execution(&command.steps[0], Trigger::Root(&[])));
+        }
+
+        #[test]
+        fn root_replaces_root_of_directory(){
+            let files = vec![File{ root: "./crates".into(), ..file() }];
+            let command = Command{ steps: vec!["echo ROOT DIR".into()], ..cmd() };
+
+            assert_eq!("echo ./crates ./crates/a", command.execution(&command.steps[0], Trigger::Directory("./crates/a", &files)));
+        }
+
+        #[test]
+        fn name_replaces_filename(){
+            let mut command = cmd();
+            command.steps = vec!["echo NAME".into()];
+
+            let expected = format!("echo {}", file().name);
+            assert_eq!(expected, command.execution(&command.steps[0], Trigger::File(&file())));
+        }
+
+        #[test]
+        fn path_replaces_path(){
+            let command = Command{ steps: vec!["echo PATH".into()], root_paths: vec!["".into()], run_mode: RunMode::File, ..cmd() };
+
+            let expected = format!("echo {}", file().path);
+            assert_eq!(expected, command.execution(&command.steps[0], Trigger::File(&file())));
+        }
+
+        #[test]
+        fn target_replaces_target(){
+            let command = Command{ steps: vec!["echo TARGET".into()], root_paths: vec!["".into()], run_mode: RunMode::File, ..cmd() };
+
+            let expected = format!("echo {}", file().target);
+            assert_eq!(expected, command.execution(&command.steps[0], Trigger::File(&file())));
+        }
+
+        #[test]
+        fn ext_replaces_ext(){
+            let command = Command{ steps: vec!["echo EXT".into()], root_paths: vec!["".into()], run_mode: RunMode::File, ..cmd() };
+
+            let expected = format!("echo {}", file().extension);
+            assert_eq!(expected, command.execution(&command.steps[0], Trigger::File(&file())));
+        }
+
+        #[test]
+        fn complex(){
+            let command = Command{ steps: vec!["echo testy-NAME-path-PATH-ext.EXT".into()], root_paths: vec!["".into()], run_mode: RunMode::File, ..cmd() };
+
+            let expected = format!("echo testy-{name}-path-{path}-ext.{ext}", name = file().name, path = file().path, ext = file().extension);
+            assert_eq!(expected, command.execution(&command.steps[0], Trigger::File(&file())));
+        }
+    });
+
+    describe!(invocations => {
+        fn files() -> Vec<File> {
+            ["a.rs", "b.rs", "c.rs"]
+                .iter()
+                .map(|p| File{ path: p.to_string(), ..file() })
+                .collect()
+        }
+
+        fn argv(s: &str) -> Vec<String> {
+            s.split_ascii_whitespace().map(String::from).collect()
+        }
+
+        /// Returns the argv of each invocation of the command's first step.
+        fn argvs(command: &Command, trigger: Trigger) -> Vec<Vec<String>> {
+            command.invocations(&command.steps[0], trigger, true).into_iter().map(|(argv, _)| argv).collect()
+        }
+
+        #[test]
+        fn without_files_returns_single_invocation(){
+            let command = Command{ steps: vec!["cargo build".into()], ..cmd() };
+            assert_eq!(vec![argv("cargo build")], argvs(&command, Trigger::Root(&files())));
+        }
+
+        #[test]
+        fn empty_command_returns_none(){
+            let command = Command{ steps: vec!["".into()], ..cmd() };
+            assert!(argvs(&command, Trigger::Root(&files())).is_empty());
+        }
+
+        #[test]
+        fn files_expands_to_every_path(){
+            let command = Command{ steps: vec!["rustfmt --check FILES --edition 2021".into()], ..cmd() };
+            let expected = vec![argv("rustfmt --check a.rs b.rs c.rs --edition 2021")];
+            assert_eq!(expected, argvs(&command, Trigger::Root(&files())));
+        }
+
+        #[test]
+        fn files_is_chunked(){
+            let command = Command{ steps: vec!["rustfmt FILES".into()], chunk_size: Some(2), ..cmd() };
+            let expected = vec![argv("rustfmt a.rs b.rs"), argv("rustfmt c.rs")];
+            assert_eq!(expected, argvs(&command, Trigger::Root(&files())));
+        }
+
+        #[test]
+        fn stdin_is_chunked(){
+            let command = Command{ steps: vec!["xargs rustfmt".into()], chunk_size: Some(2), stdin_format: Some(StdinFormat::Lines), ..cmd() };
+            let expected = vec![
+                (argv("xargs rustfmt"), vec!["a.rs".to_string(), "b.rs".to_string()]),
+                (argv("xargs rustfmt"), vec!["c.rs".to_string()]),
+            ];
+            assert_eq!(expected, command.invocations(&command.steps[0], Trigger::Root(&files()), true));
+        }
+
+        #[test]
+        fn stdin_without_changes_returns_single_invocation(){
+            let command = Command{ steps: vec!["xargs rustfmt".into()], stdin_format: Some(StdinFormat::Lines), ..cmd() };
+            assert_eq!(vec![(argv("xargs rustfmt"), vec![])], command.invocations(&command.steps[0], Trigger::Root(&[]), true));
+        }
+
+        #[test]
+        fn files_in_directory_trigger_uses_directory_files(){
+            let files = files();
+            let command = Command{ steps: vec!["rustfmt FILES".into()], ..cmd() };
+            let expected = vec![argv("rustfmt b.rs")];
+            assert_eq!(expected, argvs(&command, Trigger::Directory("./src", &files[1..2])));
+        }
+
+        #[test]
+        fn files_with_no_changes_returns_none(){
+            let command = Command{ steps: vec!["rustfmt FILES".into()], ..cmd() };
+            assert!(argvs(&command, Trigger::Root(&[])).is_empty());
+        }
+
+        #[test]
+        fn files_in_file_trigger_uses_file(){
+            let command = Command{ steps: vec!["rustfmt FILES".into()], ..cmd() };
+            let expected = vec![argv(&format!("rustfmt {}", file().path))];
+            assert_eq!(expected, argvs(&command, Trigger::File(&file())));
+        }
+    });
+
+    describe!(stdin => {
+        fn paths() -> Vec<String> {
+            vec!["a.rs".into(), "b c.rs".into()]
+        }
+
+        #[test]
+        fn no_format_returns_none(){
+            assert_eq!(None, cmd().stdin(&paths()));
+        }
+
+        #[test]
+        fn lines_separates_with_newlines(){
+            let command = Command{ stdin_format: Some(StdinFormat::Lines), ..cmd() };
+            assert_eq!(Some(b"a.rs\nb c.rs\n".to_vec()), command.stdin(&paths()));
+        }
+
+        #[test]
+        fn nul_separates_with_nul(){
+            let command = Command{ stdin_format: Some(StdinFormat::Nul), ..cmd() };
+            assert_eq!(Some(b"a.rs\0b c.rs\0".to_vec()), command.stdin(&paths()));
+        }
+    });
+
+    describe!(parse => {
+        fn parse(input: &str) -> Result<Command, CommandErr>{
+            Command::parse(input)
+        }
+
+        #[test]
+        fn empty_input_returns_err(){
+            let input = " ";
+
+            let expected = Err(CommandErr::EmptyInput);
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn missing_run_mode_returns_err(){
+            let input = " garbage day";
+
+            let expected = Err(CommandErr::MissingRunMode);
+            let actual = parse(input);
 
             assert_eq!(expected, actual);
         }
 
-        const MODES: [(RunMode, &'static str); 2] = [
+        const MODES: [(RunMode, &str); 3] = [
             (RunMode::Directory,"directory"),
-            (RunMode::File, "foreach file in")
+            (RunMode::File, "foreach file in"),
+            (RunMode::EachDirectory, "foreach dir in")
         ];
 
         #[test]
         fn missing_path_returns_err(){
             for (_, mode) in MODES{
-                let input = format!("{}", mode);
-
                 let expected = Err(CommandErr::MissingPathSpecification);
-                let actual = parse(&input);
+                let actual = parse(mode);
 
                 assert_eq!(expected, actual);
             }
@@ -353,8 +1645,8 @@ mod tests {
             for (run_mode, mode) in MODES{
                 let input = format!("{} ./src/path do end", mode);
 
-                let expected = Ok(Command{ command:
-                    "".into(), root_path: "./src/path".into(), run_mode });
+                let expected = Ok(Command{ steps:
+                    vec![], root_paths: vec!["./src/path".into()], run_mode, ..cmd() });
                 let actual = parse(&input);
 
                 assert_eq!(expected, actual);
@@ -366,14 +1658,350 @@ mod tests {
             for (run_mode, mode) in MODES{
                 let input = format!("{} ./src/path do echo \"HI\" end", mode);
 
-                let expected = Ok(Command{ command:
-                    "echo \"HI\"".into(), root_path: "./src/path".into(), run_mode });
+                let expected = Ok(Command{ steps:
+                    vec!["echo \"HI\"".into()], root_paths: vec!["./src/path".into()], run_mode, ..cmd() });
                 let actual = parse(&input);
 
                 assert_eq!(expected, actual);
             }
         }
 
+        #[test]
+        fn startup_clause_sets_startup_mode(){
+            for (run_mode, mode) in MODES{
+                for startup_mode in StartupMode::values(){
+                    let input = format!("{} ./src/path startup {} do echo end", mode, startup_mode.pretty_print());
+
+                    let expected = Ok(Command{ steps:
+                        vec!["echo".into()], root_paths: vec!["./src/path".into()], run_mode, startup_mode, ..cmd() });
+                    let actual = parse(&input);
+
+                    assert_eq!(expected, actual);
+                }
+            }
+        }
+
+        #[test]
+        fn invalid_startup_clause_returns_err(){
+            for (_, mode) in MODES{
+                let input = format!("{} ./src/path startup garbage do echo end", mode);
+
+                let expected = Err(CommandErr::InvalidStartupMode);
+                let actual = parse(&input);
+
+                assert_eq!(expected, actual);
+            }
+        }
+
+        #[test]
+        fn follow_clause_follows_links(){
+            for (run_mode, mode) in MODES{
+                let input = format!("{} ./src/path follow do echo end", mode);
+
+                let expected = Ok(Command{ steps:
+                    vec!["echo".into()], root_paths: vec!["./src/path".into()], run_mode, walk_options: WalkOptions{ follow_links: true, ..Default::default() }, ..cmd() });
+                let actual = parse(&input);
+
+                assert_eq!(expected, actual);
+            }
+        }
+
+        #[test]
+        fn chunk_clause_sets_chunk_size(){
+            let input = "directory . chunk 100 do rustfmt FILES end";
+
+            let expected = Ok(Command{ steps:
+                vec!["rustfmt FILES".into()], root_paths: vec![".".into()], run_mode: RunMode::Directory, chunk_size: Some(100), ..cmd() });
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn invalid_chunk_clause_returns_err(){
+            assert_eq!(Err(CommandErr::InvalidChunkSize), parse("directory . chunk 0 do rustfmt FILES end"));
+            assert_eq!(Err(CommandErr::InvalidChunkSize), parse("directory . chunk many do rustfmt FILES end"));
+        }
+
+        #[test]
+        fn stdin_clause_sets_stdin_format(){
+            for format in StdinFormat::values(){
+                let input = format!("directory . stdin {} do xargs -0 echo end", format.pretty_print());
+
+                let expected = Ok(Command{ steps:
+                    vec!["xargs -0 echo".into()], root_paths: vec![".".into()], run_mode: RunMode::Directory, stdin_format: Some(format), ..cmd() });
+                let actual = parse(&input);
+
+                assert_eq!(expected, actual);
+            }
+        }
+
+        #[test]
+        fn invalid_stdin_clause_returns_err(){
+            assert_eq!(Err(CommandErr::InvalidStdinFormat), parse("directory . stdin csv do cat end"));
+        }
+
+        #[test]
+        fn parallel_clause_sets_parallelism(){
+            let input = "foreach file in . parallel 4 do convert PATH end";
+
+            let expected = Ok(Command{ steps:
+                vec!["convert PATH".into()], root_paths: vec![".".into()], parallelism: 4, ..cmd() });
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn parallel_clause_defaults_to_cpu_count(){
+            let input = "foreach file in . parallel do convert PATH end";
+
+            let parallelism = thread::available_parallelism().map_or(1, |n| n.get());
+            let expected = Ok(Command{ steps:
+                vec!["convert PATH".into()], root_paths: vec![".".into()], parallelism, ..cmd() });
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn invalid_parallel_clause_returns_err(){
+            assert_eq!(Err(CommandErr::InvalidParallelism), parse("foreach file in . parallel 0 do convert PATH end"));
+            assert_eq!(Err(CommandErr::InvalidParallelism), parse("foreach file in . parallel -2 do convert PATH end"));
+        }
+
+        #[test]
+        fn traversal_clauses_set_walk_options(){
+            let input = "foreach file in . maxdepth 3 mindepth 1 nohidden do echo end";
+
+            let walk_options = WalkOptions{ max_depth: Some(3), min_depth: Some(1), skip_hidden: true, ..Default::default() };
+            let expected = Ok(Command{ steps:
+                vec!["echo".into()], root_paths: vec![".".into()], run_mode: RunMode::File, walk_options, ..cmd() });
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn invalid_depth_clauses_return_err(){
+            assert_eq!(Err(CommandErr::InvalidMaxDepth), parse("foreach file in . maxdepth two do echo end"));
+            assert_eq!(Err(CommandErr::InvalidMinDepth), parse("foreach file in . mindepth -1 do echo end"));
+        }
+
+        #[test]
+        fn multiple_paths_are_parsed(){
+            for (run_mode, mode) in MODES{
+                let input = format!("{} ./src ./tests ./benches nohidden do echo end", mode);
+
+                let walk_options = WalkOptions{ skip_hidden: true, ..Default::default() };
+                let expected = Ok(Command{ steps:
+                    vec!["echo".into()], root_paths: vec!["./src".into(), "./tests".into(), "./benches".into()], run_mode, walk_options, ..cmd() });
+                let actual = parse(&input);
+
+                assert_eq!(expected, actual);
+            }
+        }
+
+        #[test]
+        fn bracketed_paths_are_parsed(){
+            for (run_mode, mode) in MODES{
+                let input = format!("{} [./src, ./tests ./benches] do echo end", mode);
+
+                let expected = Ok(Command{ steps:
+                    vec!["echo".into()], root_paths: vec!["./src".into(), "./tests".into(), "./benches".into()], run_mode, ..cmd() });
+                let actual = parse(&input);
+
+                assert_eq!(expected, actual);
+            }
+        }
+
+        #[test]
+        fn unclosed_bracketed_paths_return_err(){
+            assert_eq!(Err(CommandErr::MissingPathListEnd), parse("directory [./src, ./tests do echo end"));
+        }
+
+        #[test]
+        fn empty_bracketed_paths_return_err(){
+            assert_eq!(Err(CommandErr::MissingPathSpecification), parse("directory [] do echo end"));
+        }
+
+        #[test]
+        fn task_sets_name(){
+            let input = "task build: foreach file in ./src do cargo build end";
+
+            let expected = Ok(Command{ steps:
+                vec!["cargo build".into()], root_paths: vec!["./src".into()], name: Some("build".into()), ..cmd() });
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn task_sets_dependencies(){
+            let input = "task test after build, lint : directory . do cargo test end";
+
+            let expected = Ok(Command{ steps:
+                vec!["cargo test".into()], root_paths: vec![".".into()], run_mode: RunMode::Directory, name: Some("test".into()), dependencies: vec!["build".into(), "lint".into()], ..cmd() });
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn task_without_separator_returns_err(){
+            assert_eq!(Err(CommandErr::MissingTaskSeparator), parse("task build directory . do cargo build end"));
+        }
+
+        #[test]
+        fn invalid_task_headers_return_err(){
+            for header in ["", "after build", "build lint", "build after"]{
+                let input = format!("task {}: directory . do cargo build end", header);
+                assert_eq!(Err(CommandErr::InvalidTaskHeader), parse(&input));
+            }
+        }
+
+        #[test]
+        fn then_separates_steps(){
+            let input = "directory . do cargo fmt then cargo clippy then cargo test end";
+
+            let expected = Ok(Command{ steps: vec!["cargo fmt".into(), "cargo clippy".into(), "cargo test".into()], root_paths: vec![".".into()], run_mode: RunMode::Directory, ..cmd() });
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn empty_steps_return_err(){
+            assert_eq!(Err(CommandErr::EmptyStep), parse("directory . do cargo fmt then end"));
+            assert_eq!(Err(CommandErr::EmptyStep), parse("directory . do then cargo fmt end"));
+            assert_eq!(Err(CommandErr::EmptyStep), parse("directory . do cargo fmt then then cargo test end"));
+        }
+
+        #[test]
+        fn escaped_keywords_are_literal(){
+            let input = r"directory . do echo the \end is near \then \\end then echo done end";
+
+            let expected = Ok(Command{ steps: vec![r"echo the end is near then \end".into(), "echo done".into()], root_paths: vec![".".into()], run_mode: RunMode::Directory, ..cmd() });
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn errors_return_offset(){
+            assert_eq!(Err((CommandErr::InvalidChunkSize, 12)), Command::parse_at("directory . chunk 0 do echo end"));
+            assert_eq!(Err((CommandErr::MissingKeywordEnd, 21)), Command::parse_at("  directory . do echo a  "));
+            assert_eq!(Err((CommandErr::UnexpectedInput, 24)), Command::parse_at("directory . do echo end garbage"));
+        }
+
+        #[test]
+        fn leftover_input_returns_err(){
+            assert_eq!(Err(CommandErr::UnexpectedInput), parse("directory . do echo the end is near end"));
+            assert_eq!(Err(CommandErr::UnexpectedInput), parse("directory . do echo end on failure do echo end garbage"));
+        }
+
+        #[test]
+        fn on_clauses_set_handlers(){
+            let input = "foreach file in ./src do cargo build end on failure do notify-send PATH EXIT_CODE end on success do echo ok then echo done end";
+
+            let expected = Ok(Command{
+                steps: vec!["cargo build".into()],
+                root_paths: vec!["./src".into()],
+                on_failure: vec!["notify-send PATH EXIT_CODE".into()],
+                on_success: vec!["echo ok".into(), "echo done".into()],
+                ..cmd()
+            });
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn invalid_on_clauses_return_err(){
+            assert_eq!(Err(CommandErr::InvalidHandler), parse("directory . do echo end on garbage do echo end"));
+            assert_eq!(Err(CommandErr::MissingKeywordDo), parse("directory . do echo end on failure echo end"));
+            assert_eq!(Err(CommandErr::MissingKeywordEnd), parse("directory . do echo end on failure do echo"));
+        }
+
+        #[test]
+        fn with_env_clause_sets_environment(){
+            let input = "directory . with env RUST_BACKTRACE=1 LOG=debug do cargo test end";
+
+            let environment = BTreeMap::from([("LOG".to_string(), "debug".to_string()), ("RUST_BACKTRACE".to_string(), "1".to_string())]);
+            let expected = Ok(Command{ steps: vec!["cargo test".into()], root_paths: vec![".".into()], run_mode: RunMode::Directory, environment, ..cmd() });
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn invalid_with_env_clauses_return_err(){
+            assert_eq!(Err(CommandErr::InvalidEnvironment), parse("directory . with RUST_BACKTRACE=1 do cargo test end"));
+            assert_eq!(Err(CommandErr::InvalidEnvironment), parse("directory . with env do cargo test end"));
+            assert_eq!(Err(CommandErr::InvalidEnvironment), parse("directory . with env =1 do cargo test end"));
+        }
+
+        #[test]
+        fn where_clause_sets_filters(){
+            let input = "foreach file in ./src where EXT in [rs, toml] where NAME in lib.rs do cargo check end";
+
+            let filters = vec![(ScriptOptions::Ext, vec!["rs".into(), "toml".into()]), (ScriptOptions::Name, vec!["lib.rs".into()])];
+            let expected = Ok(Command{ steps: vec!["cargo check".into()], root_paths: vec!["./src".into()], filters, ..cmd() });
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn invalid_where_clauses_return_err(){
+            assert_eq!(Err(CommandErr::InvalidWhere), parse("directory . where FILES in [a] do echo end"));
+            assert_eq!(Err(CommandErr::InvalidWhere), parse("directory . where EXT [rs] do echo end"));
+            assert_eq!(Err(CommandErr::InvalidWhere), parse("directory . where EXT in [rs do echo end"));
+            assert_eq!(Err(CommandErr::InvalidWhere), parse("directory . where EXT in [] do echo end"));
+            assert_eq!(Err(CommandErr::InvalidWhere), parse("directory . where EXT in do echo end"));
+        }
+
+        #[test]
+        fn envfile_clause_sets_env_file(){
+            let input = "directory . envfile ./config/.env do cargo run end";
+
+            let expected = Ok(Command{ steps: vec!["cargo run".into()], root_paths: vec![".".into()], run_mode: RunMode::Directory, env_file: Some("./config/.env".into()), ..cmd() });
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn invalid_envfile_clause_returns_err(){
+            assert_eq!(Err(CommandErr::InvalidEnvFile), parse("directory . envfile do cargo run end"));
+        }
+
+        #[test]
+        fn cwd_clause_sets_cwd(){
+            let input = "foreach file in ./frontend cwd ./frontend do npm run build end";
+
+            let expected = Ok(Command{ steps: vec!["npm run build".into()], root_paths: vec!["./frontend".into()], cwd: Some("./frontend".into()), ..cmd() });
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn invalid_cwd_clause_returns_err(){
+            assert_eq!(Err(CommandErr::InvalidCwd), parse("foreach file in ./frontend cwd do npm run build end"));
+        }
+
+        #[test]
+        fn keywords_in_command_are_preserved(){
+            let input = "directory . do echo directory . done end";
+
+            let expected = Ok(Command{ steps:
+                vec!["echo directory . done".into()], root_paths: vec![".".into()], run_mode: RunMode::Directory, ..cmd() });
+            let actual = parse(input);
+
+            assert_eq!(expected, actual);
+        }
+
         #[test]
         fn validate_command_values(){
           todo!("Ensure that interpolated values are whitelisted")
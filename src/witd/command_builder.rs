@@ -0,0 +1,316 @@
+use super::{
+    Command, CommandErr, Keyword, Outcome, RunMode, ScriptOptions, StartupMode, StdinFormat,
+};
+use crate::types::WalkOptions;
+use std::collections::BTreeMap;
+
+/// Builds a command programmatically, as an alternative to parsing its syntax.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandBuilder {
+    command: Command,
+}
+
+impl Command {
+    /// Returns a builder for a command with the given run mode.
+    pub fn builder(run_mode: RunMode) -> CommandBuilder {
+        CommandBuilder::new(run_mode)
+    }
+}
+
+impl CommandBuilder {
+    /// Creates a new builder for a command with the given run mode.
+    pub fn new(run_mode: RunMode) -> Self {
+        Self {
+            command: Command {
+                chunk_size: None,
+                cwd: None,
+                dependencies: vec![],
+                dry_run: false,
+                env_file: None,
+                environment: BTreeMap::new(),
+                filters: vec![],
+                name: None,
+                on_failure: vec![],
+                on_success: vec![],
+                parallelism: 1,
+                root_paths: vec![],
+                run_mode,
+                startup_mode: StartupMode::default(),
+                stdin_format: None,
+                steps: vec![],
+                walk_options: WalkOptions::default(),
+            },
+        }
+    }
+
+    /// Adds a task this command runs after, making the command a task.
+    pub fn after(mut self, dependency: &str) -> Self {
+        self.command.dependencies.push(dependency.to_string());
+        self
+    }
+
+    /// Validates and returns the command.
+    pub fn build(self) -> Result<Command, CommandErr> {
+        let command = self.command;
+
+        let is_invalid_name = |name: &str| {
+            name.is_empty()
+                || name.contains(|c: char| c == ',' || c == ':' || c.is_whitespace())
+                || Keyword::parse(name).is_some()
+        };
+        let is_invalid_path = |path: &str| path.is_empty() || Keyword::parse(path).is_some();
+
+        if command.root_paths.is_empty() {
+            return Err(CommandErr::MissingPathSpecification);
+        }
+        if command.name.as_deref().is_some_and(is_invalid_name)
+            || (command.name.is_none() && !command.dependencies.is_empty())
+            || command.dependencies.iter().any(|d| is_invalid_name(d))
+        {
+            return Err(CommandErr::InvalidTaskHeader);
+        }
+        if command.chunk_size == Some(0) {
+            return Err(CommandErr::InvalidChunkSize);
+        }
+        if command.cwd.as_deref().is_some_and(is_invalid_path) {
+            return Err(CommandErr::InvalidCwd);
+        }
+        if command.env_file.as_deref().is_some_and(is_invalid_path) {
+            return Err(CommandErr::InvalidEnvFile);
+        }
+        if command.environment.keys().any(|k| k.is_empty()) {
+            return Err(CommandErr::InvalidEnvironment);
+        }
+        if command.parallelism == 0 {
+            return Err(CommandErr::InvalidParallelism);
+        }
+        if command.filters.iter().any(|(option, values)| {
+            !ScriptOptions::filters().contains(option)
+                || values.is_empty()
+                || values.iter().any(|v| is_invalid_path(v))
+        }) {
+            return Err(CommandErr::InvalidWhere);
+        }
+        if command
+            .steps
+            .iter()
+            .chain(command.on_failure.iter())
+            .chain(command.on_success.iter())
+            .any(String::is_empty)
+        {
+            return Err(CommandErr::EmptyStep);
+        }
+
+        Ok(command)
+    }
+
+    /// Sets the number of paths passed to each invocation, from `FILES` or on stdin, in any mode.
+    pub fn chunk(mut self, size: usize) -> Self {
+        self.command.chunk_size = Some(size);
+        self
+    }
+
+    /// Sets the directory the steps are executed in.
+    pub fn cwd(mut self, path: &str) -> Self {
+        self.command.cwd = Some(path.to_string());
+        self
+    }
+
+    /// Sets an environment variable for the steps.
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.command
+            .environment
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Sets the env file to load variables from.
+    pub fn env_file(mut self, path: &str) -> Self {
+        self.command.env_file = Some(path.to_string());
+        self
+    }
+
+    /// Sets whether symlinks are followed.
+    pub fn follow_links(mut self, follow_links: bool) -> Self {
+        self.command.walk_options.follow_links = follow_links;
+        self
+    }
+
+    /// Sets the maximum depth to descend to.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.command.walk_options.max_depth = Some(depth);
+        self
+    }
+
+    /// Sets the minimum depth a file must be at.
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.command.walk_options.min_depth = Some(depth);
+        self
+    }
+
+    /// Sets the task name, making the command a task.
+    pub fn name(mut self, name: &str) -> Self {
+        self.command.name = Some(name.to_string());
+        self
+    }
+
+    /// Adds a step to execute for the given outcome.
+    pub fn on(mut self, outcome: Outcome, step: &str) -> Self {
+        let step = normalize(step);
+        match outcome {
+            Outcome::Failure => self.command.on_failure.push(step),
+            Outcome::Success => self.command.on_success.push(step),
+        }
+        self
+    }
+
+    /// Sets the maximum number of jobs that may run at once.
+    pub fn parallel(mut self, jobs: usize) -> Self {
+        self.command.parallelism = jobs;
+        self
+    }
+
+    /// Adds a root path to watch.
+    pub fn path(mut self, path: &str) -> Self {
+        self.command.root_paths.push(path.to_string());
+        self
+    }
+
+    /// Sets whether dotfiles and dot-directories are skipped.
+    pub fn skip_hidden(mut self, skip_hidden: bool) -> Self {
+        self.command.walk_options.skip_hidden = skip_hidden;
+        self
+    }
+
+    /// Sets what happens to the files found on the initial scan.
+    pub fn startup(mut self, startup_mode: StartupMode) -> Self {
+        self.command.startup_mode = startup_mode;
+        self
+    }
+
+    /// Sets the format changed paths are written to stdin in.
+    pub fn stdin(mut self, stdin_format: StdinFormat) -> Self {
+        self.command.stdin_format = Some(stdin_format);
+        self
+    }
+
+    /// Adds a step to execute; each step runs only if the previous one succeeded.
+    pub fn step(mut self, step: &str) -> Self {
+        self.command.steps.push(normalize(step));
+        self
+    }
+
+    /// Only watches files where the script option has one of the values, e.g. `EXT` in `[rs]`.
+    pub fn where_in(mut self, option: ScriptOptions, values: &[&str]) -> Self {
+        let values = values.iter().map(|v| v.to_string()).collect();
+        self.command.filters.push((option, values));
+        self
+    }
+}
+
+/// Collapses the whitespace in a step the same way parsing does.
+fn normalize(step: &str) -> String {
+    step.split_ascii_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    describe!(build => {
+        #[test]
+        fn matches_parsed_command(){
+            let expected = Command::parse("foreach file in ./src do echo PATH end");
+            let actual = Command::builder(RunMode::File).path("./src").step("echo  PATH").build();
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn matches_parsed_command_with_every_clause(){
+            let expected = Command::parse(
+                "task test after build, lint: foreach file in [./src, ./tests] chunk 2 cwd ./web envfile .env.local follow maxdepth 3 mindepth 1 nohidden parallel 4 startup once stdin nul where EXT in [rs, toml] with env A=1 do cargo test then echo done end on failure do echo failed end on success do echo passed end"
+            );
+            let actual = CommandBuilder::new(RunMode::File)
+                .name("test")
+                .after("build")
+                .after("lint")
+                .path("./src")
+                .path("./tests")
+                .chunk(2)
+                .cwd("./web")
+                .env_file(".env.local")
+                .follow_links(true)
+                .max_depth(3)
+                .min_depth(1)
+                .skip_hidden(true)
+                .parallel(4)
+                .startup(StartupMode::Once)
+                .stdin(StdinFormat::Nul)
+                .where_in(ScriptOptions::Ext, &["rs", "toml"])
+                .env("A", "1")
+                .step("cargo test")
+                .step("echo done")
+                .on(Outcome::Failure, "echo failed")
+                .on(Outcome::Success, "echo passed")
+                .build();
+
+            assert_eq!(expected, actual);
+        }
+
+        #[test]
+        fn missing_path_returns_err(){
+            assert_eq!(Err(CommandErr::MissingPathSpecification), CommandBuilder::new(RunMode::Directory).step("echo").build());
+        }
+
+        #[test]
+        fn invalid_name_returns_err(){
+            for name in ["", "a b", "do"]{
+                let actual = CommandBuilder::new(RunMode::Directory).name(name).path(".").build();
+                assert_eq!(Err(CommandErr::InvalidTaskHeader), actual);
+            }
+        }
+
+        #[test]
+        fn dependency_without_name_returns_err(){
+            let actual = CommandBuilder::new(RunMode::Directory).after("build").path(".").build();
+            assert_eq!(Err(CommandErr::InvalidTaskHeader), actual);
+        }
+
+        #[test]
+        fn zero_chunk_size_returns_err(){
+            assert_eq!(Err(CommandErr::InvalidChunkSize), CommandBuilder::new(RunMode::File).path(".").chunk(0).build());
+        }
+
+        #[test]
+        fn invalid_paths_return_err(){
+            assert_eq!(Err(CommandErr::InvalidCwd), CommandBuilder::new(RunMode::File).path(".").cwd("").build());
+            assert_eq!(Err(CommandErr::InvalidEnvFile), CommandBuilder::new(RunMode::File).path(".").env_file("end").build());
+        }
+
+        #[test]
+        fn empty_env_key_returns_err(){
+            assert_eq!(Err(CommandErr::InvalidEnvironment), CommandBuilder::new(RunMode::File).path(".").env("", "1").build());
+        }
+
+        #[test]
+        fn zero_parallelism_returns_err(){
+            assert_eq!(Err(CommandErr::InvalidParallelism), CommandBuilder::new(RunMode::File).path(".").parallel(0).build());
+        }
+
+        #[test]
+        fn invalid_where_returns_err(){
+            let builder = || CommandBuilder::new(RunMode::File).path(".");
+            assert_eq!(Err(CommandErr::InvalidWhere), builder().where_in(ScriptOptions::Files, &["a"]).build());
+            assert_eq!(Err(CommandErr::InvalidWhere), builder().where_in(ScriptOptions::Ext, &[]).build());
+            assert_eq!(Err(CommandErr::InvalidWhere), builder().where_in(ScriptOptions::Ext, &[""]).build());
+        }
+
+        #[test]
+        fn empty_step_returns_err(){
+            assert_eq!(Err(CommandErr::EmptyStep), CommandBuilder::new(RunMode::File).path(".").step("  ").build());
+            assert_eq!(Err(CommandErr::EmptyStep), CommandBuilder::new(RunMode::File).path(".").on(Outcome::Success, "").build());
+        }
+    });
+}
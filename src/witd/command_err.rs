@@ -1,21 +1,115 @@
 use crate::traits::PrettyPrint;
 
-use super::{Keyword, RunMode};
+use super::{Keyword, Outcome, RunMode, ScriptOptions, StartupMode, StdinFormat};
 
 /// An error that may occur for a command.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum CommandErr {
     EmptyInput,
+    EmptyStep,
+    InvalidChunkSize,
+    InvalidCwd,
+    InvalidEnvFile,
+    InvalidEnvironment,
+    InvalidHandler,
+    InvalidMaxDepth,
+    InvalidMinDepth,
+    InvalidParallelism,
+    InvalidStartupMode,
+    InvalidStdinFormat,
+    InvalidTaskHeader,
+    InvalidWhere,
     MissingKeywordDo,
     MissingKeywordEnd,
     MissingRunMode,
     MissingPathSpecification,
+    MissingPathListEnd,
+    MissingTaskSeparator,
+    UnexpectedInput,
 }
 
 impl PrettyPrint for CommandErr {
     fn pretty_print(&self) -> String {
         match self {
             CommandErr::EmptyInput => "Empty input provided!".into(),
+            CommandErr::EmptyStep => format!(
+                "Empty step; every '{}' must be between two commands.",
+                Keyword::Then.pretty_print()
+            ),
+            CommandErr::InvalidChunkSize => format!(
+                "Invalid '{}'; expected a number greater than zero.",
+                Keyword::Chunk.pretty_print()
+            ),
+            CommandErr::InvalidCwd => {
+                format!(
+                    "Invalid '{}'; expected a path.",
+                    Keyword::Cwd.pretty_print()
+                )
+            }
+            CommandErr::InvalidEnvFile => format!(
+                "Invalid '{}'; expected a path.",
+                Keyword::EnvFile.pretty_print()
+            ),
+            CommandErr::InvalidEnvironment => format!(
+                "Invalid '{} {}'; expected one or more KEY=VALUE pairs.",
+                Keyword::With.pretty_print(),
+                Keyword::Env.pretty_print()
+            ),
+            CommandErr::InvalidHandler => format!(
+                "Invalid '{}'; options are [{}].",
+                Keyword::On.pretty_print(),
+                Outcome::values()
+                    .iter()
+                    .map(|o| o.pretty_print())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            CommandErr::InvalidMaxDepth => format!(
+                "Invalid '{}'; expected a whole number.",
+                Keyword::MaxDepth.pretty_print()
+            ),
+            CommandErr::InvalidMinDepth => format!(
+                "Invalid '{}'; expected a whole number.",
+                Keyword::MinDepth.pretty_print()
+            ),
+            CommandErr::InvalidParallelism => format!(
+                "Invalid '{}'; expected a number greater than zero.",
+                Keyword::Parallel.pretty_print()
+            ),
+            CommandErr::InvalidStartupMode => format!(
+                "Invalid '{}'; options are [{}].",
+                Keyword::Startup.pretty_print(),
+                StartupMode::values()
+                    .iter()
+                    .map(|m| m.pretty_print())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            CommandErr::InvalidStdinFormat => format!(
+                "Invalid '{}'; options are [{}].",
+                Keyword::Stdin.pretty_print(),
+                StdinFormat::values()
+                    .iter()
+                    .map(|f| f.pretty_print())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            CommandErr::InvalidTaskHeader => format!(
+                "Invalid '{}'; expected '{task} NAME:' or '{task} NAME {} DEPENDENCIES:'.",
+                Keyword::Task.pretty_print(),
+                Keyword::After.pretty_print(),
+                task = Keyword::Task.pretty_print()
+            ),
+            CommandErr::InvalidWhere => format!(
+                "Invalid '{}'; expected 'OPTION {} [VALUES]' where OPTION is one of [{}].",
+                Keyword::Where.pretty_print(),
+                Keyword::In.pretty_print(),
+                ScriptOptions::filters()
+                    .iter()
+                    .map(|o| o.pretty_print())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
             CommandErr::MissingKeywordDo => format!("Missing '{}'!", Keyword::Do.pretty_print()),
             CommandErr::MissingKeywordEnd => format!("Missing '{}'!", Keyword::End.pretty_print()),
             CommandErr::MissingRunMode => format!(
@@ -28,6 +122,14 @@ impl PrettyPrint for CommandErr {
                     .join(", ")
             ),
             CommandErr::MissingPathSpecification => "Missing path specification!".into(),
+            CommandErr::MissingPathListEnd => "Missing ']' for the list of paths!".into(),
+            CommandErr::MissingTaskSeparator => {
+                format!("Missing ':' after '{}'!", Keyword::Task.pretty_print())
+            }
+            CommandErr::UnexpectedInput => format!(
+                "Unexpected input after '{end}'; a literal '{end}' in a step is written '\\{end}'.",
+                end = Keyword::End.pretty_print()
+            ),
         }
     }
 }
@@ -43,6 +145,71 @@ mod tests {
             assert_eq!("Empty input provided!", CommandErr::EmptyInput.pretty_print());
         }
 
+        #[test]
+        fn empty_step(){
+            assert_eq!("Empty step; every 'then' must be between two commands.", CommandErr::EmptyStep.pretty_print());
+        }
+
+        #[test]
+        fn invalid_chunk_size(){
+            assert_eq!("Invalid 'chunk'; expected a number greater than zero.", CommandErr::InvalidChunkSize.pretty_print());
+        }
+
+        #[test]
+        fn invalid_cwd(){
+            assert_eq!("Invalid 'cwd'; expected a path.", CommandErr::InvalidCwd.pretty_print());
+        }
+
+        #[test]
+        fn invalid_env_file(){
+            assert_eq!("Invalid 'envfile'; expected a path.", CommandErr::InvalidEnvFile.pretty_print());
+        }
+
+        #[test]
+        fn invalid_environment(){
+            assert_eq!("Invalid 'with env'; expected one or more KEY=VALUE pairs.", CommandErr::InvalidEnvironment.pretty_print());
+        }
+
+        #[test]
+        fn invalid_handler(){
+            assert_eq!("Invalid 'on'; options are [failure, success].", CommandErr::InvalidHandler.pretty_print());
+        }
+
+        #[test]
+        fn invalid_max_depth(){
+            assert_eq!("Invalid 'maxdepth'; expected a whole number.", CommandErr::InvalidMaxDepth.pretty_print());
+        }
+
+        #[test]
+        fn invalid_min_depth(){
+            assert_eq!("Invalid 'mindepth'; expected a whole number.", CommandErr::InvalidMinDepth.pretty_print());
+        }
+
+        #[test]
+        fn invalid_parallelism(){
+            assert_eq!("Invalid 'parallel'; expected a number greater than zero.", CommandErr::InvalidParallelism.pretty_print());
+        }
+
+        #[test]
+        fn invalid_startup_mode(){
+            assert_eq!("Invalid 'startup'; options are [all, none, once].", CommandErr::InvalidStartupMode.pretty_print());
+        }
+
+        #[test]
+        fn invalid_stdin_format(){
+            assert_eq!("Invalid 'stdin'; options are [lines, nul].", CommandErr::InvalidStdinFormat.pretty_print());
+        }
+
+        #[test]
+        fn invalid_task_header(){
+            assert_eq!("Invalid 'task'; expected 'task NAME:' or 'task NAME after DEPENDENCIES:'.", CommandErr::InvalidTaskHeader.pretty_print());
+        }
+
+        #[test]
+        fn invalid_where(){
+            assert_eq!("Invalid 'where'; expected 'OPTION in [VALUES]' where OPTION is one of [DIR, EXT, NAME, PATH, RELPATH, ROOT, TARGET].", CommandErr::InvalidWhere.pretty_print());
+        }
+
         #[test]
         fn missing_keyword_do(){
             assert_eq!(format!("Missing '{}'!", Keyword::Do.pretty_print()), CommandErr::MissingKeywordDo.pretty_print());
@@ -55,12 +222,27 @@ mod tests {
 
         #[test]
         fn missing_run_mode(){
-            assert_eq!(format!("Missing 'mode'; options are [directory, file]."), CommandErr::MissingRunMode.pretty_print());
+            assert_eq!("Missing 'mode'; options are [directory, file, dir].", CommandErr::MissingRunMode.pretty_print());
         }
 
         #[test]
         fn missing_path_specification(){
             assert_eq!("Missing path specification!", CommandErr::MissingPathSpecification.pretty_print());
         }
+
+        #[test]
+        fn missing_path_list_end(){
+            assert_eq!("Missing ']' for the list of paths!", CommandErr::MissingPathListEnd.pretty_print());
+        }
+
+        #[test]
+        fn missing_task_separator(){
+            assert_eq!("Missing ':' after 'task'!", CommandErr::MissingTaskSeparator.pretty_print());
+        }
+
+        #[test]
+        fn unexpected_input(){
+            assert_eq!("Unexpected input after 'end'; a literal 'end' in a step is written '\\end'.", CommandErr::UnexpectedInput.pretty_print());
+        }
     });
 }
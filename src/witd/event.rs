@@ -0,0 +1,406 @@
+use crate::{summary::Summary, traits::PrettyPrint};
+use std::time::Duration;
+
+/// Why a file was detected as changed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Change {
+    Created,
+    Modified,
+}
+
+impl PrettyPrint for Change {
+    fn pretty_print(&self) -> String {
+        match self {
+            Change::Created => "created",
+            Change::Modified => "modified",
+        }
+        .into()
+    }
+}
+
+/// Why a command was triggered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reason {
+    /// Files changed since they were last seen.
+    Changed,
+    /// A task it depends on succeeded.
+    Dependency,
+    /// Every command is run once with `--once`.
+    Once,
+    /// Files were found on the initial scan.
+    Startup,
+}
+
+impl PrettyPrint for Reason {
+    fn pretty_print(&self) -> String {
+        match self {
+            Reason::Changed => "changed",
+            Reason::Dependency => "dependency",
+            Reason::Once => "once",
+            Reason::Startup => "startup",
+        }
+        .into()
+    }
+}
+
+/// The output stream of a process.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stream {
+    Stderr,
+    Stdout,
+}
+
+impl PrettyPrint for Stream {
+    fn pretty_print(&self) -> String {
+        match self {
+            Stream::Stderr => "stderr",
+            Stream::Stdout => "stdout",
+        }
+        .into()
+    }
+}
+
+/// Something that happened while watching or executing, to be reported as text or JSON.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// The argv that would be executed in a dry run.
+    DryRun {
+        task: String,
+        argv: Vec<String>,
+        cwd: Option<String>,
+    },
+    Error {
+        task: Option<String>,
+        message: String,
+    },
+    /// A process exited; a process killed by a signal exits with 1.
+    Exit {
+        task: String,
+        pid: u32,
+        code: i32,
+        duration: Duration,
+    },
+    /// The output of a process, reported once it exits so parallel processes never interleave.
+    Output {
+        task: String,
+        pid: u32,
+        stream: Stream,
+        lines: Vec<String>,
+    },
+    /// The number of files and errors found by a scan, reported whenever either count changes.
+    Scan {
+        task: String,
+        files: usize,
+        errors: usize,
+    },
+    Start {
+        task: String,
+        pid: u32,
+        argv: Vec<String>,
+        cwd: Option<String>,
+    },
+    Summary(Summary),
+    /// A command was triggered, along with the file and change that triggered it, if any.
+    Triggered {
+        task: String,
+        path: Option<String>,
+        change: Option<Change>,
+        reason: Reason,
+    },
+    Warning {
+        message: String,
+    },
+}
+
+impl Event {
+    /// Returns the event as newline delimited JSON records, one per line of output.
+    pub fn to_json(&self) -> Vec<String> {
+        match self {
+            Event::DryRun { task, argv, cwd } => vec![record(
+                "dry_run",
+                &[
+                    ("task", json_string(task)),
+                    ("argv", json_array(argv)),
+                    ("cwd", json_option(cwd)),
+                ],
+            )],
+            Event::Error { task, message } => vec![record(
+                "error",
+                &[
+                    ("task", json_option(task)),
+                    ("message", json_string(message)),
+                ],
+            )],
+            Event::Exit {
+                task,
+                pid,
+                code,
+                duration,
+            } => vec![record(
+                "exit",
+                &[
+                    ("task", json_string(task)),
+                    ("pid", pid.to_string()),
+                    ("code", code.to_string()),
+                    ("duration_ms", duration.as_millis().to_string()),
+                ],
+            )],
+            Event::Output {
+                task,
+                pid,
+                stream,
+                lines,
+            } => lines
+                .iter()
+                .map(|line| {
+                    record(
+                        "output",
+                        &[
+                            ("task", json_string(task)),
+                            ("pid", pid.to_string()),
+                            ("stream", json_string(&stream.pretty_print())),
+                            ("line", json_string(line)),
+                        ],
+                    )
+                })
+                .collect(),
+            Event::Scan {
+                task,
+                files,
+                errors,
+            } => vec![record(
+                "scan",
+                &[
+                    ("task", json_string(task)),
+                    ("files", files.to_string()),
+                    ("errors", errors.to_string()),
+                ],
+            )],
+            Event::Start {
+                task,
+                pid,
+                argv,
+                cwd,
+            } => vec![record(
+                "start",
+                &[
+                    ("task", json_string(task)),
+                    ("pid", pid.to_string()),
+                    ("argv", json_array(argv)),
+                    ("cwd", json_option(cwd)),
+                ],
+            )],
+            Event::Summary(summary) => {
+                let tasks: Vec<String> = summary
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        format!(
+                            "{{\"task\":{},\"result\":{},\"duration_ms\":{}}}",
+                            json_string(&row.task),
+                            json_string(&row.result.pretty_print()),
+                            row.duration
+                                .map_or("null".to_string(), |d| d.as_millis().to_string())
+                        )
+                    })
+                    .collect();
+
+                vec![record(
+                    "summary",
+                    &[
+                        ("passed", summary.passed().to_string()),
+                        ("tasks", format!("[{}]", tasks.join(","))),
+                    ],
+                )]
+            }
+            Event::Triggered {
+                task,
+                path,
+                change,
+                reason,
+            } => vec![record(
+                "trigger",
+                &[
+                    ("task", json_string(task)),
+                    ("path", json_option(path)),
+                    ("change", json_option(&change.map(|c| c.pretty_print()))),
+                    ("reason", json_string(&reason.pretty_print())),
+                ],
+            )],
+            Event::Warning { message } => {
+                vec![record("warning", &[("message", json_string(message))])]
+            }
+        }
+    }
+
+    /// Returns the event as human readable text, if it is shown in text output.
+    /// Only the stdout of processes is shown, as it always has been.
+    pub fn to_text(&self) -> Option<String> {
+        match self {
+            Event::DryRun { argv, cwd, .. } => {
+                let cwd = cwd
+                    .as_ref()
+                    .map_or(String::new(), |cwd| format!(" (in {})", quote(cwd)));
+                let argv: Vec<String> = argv.iter().map(|a| quote(a)).collect();
+                Some(format!("Dry run{}: {}", cwd, argv.join(" ")))
+            }
+            Event::Error { message, .. } => Some(format!("Error: {}", message)),
+            Event::Output {
+                stream: Stream::Stdout,
+                lines,
+                ..
+            } => Some(lines.join("\n")),
+            Event::Summary(summary) => Some(summary.pretty_print()),
+            Event::Warning { message } => Some(format!("Warning: {}", message)),
+            _ => None,
+        }
+    }
+}
+
+/// Quotes the argument for display so it reads back as a single shell word.
+pub fn quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return arg.to_string();
+    }
+
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Returns a JSON object with the given type and fields, whose values are already JSON.
+fn record(kind: &str, fields: &[(&str, String)]) -> String {
+    let mut record = format!("{{\"type\":{}", json_string(kind));
+    for (key, value) in fields {
+        record.push_str(&format!(",{}:{}", json_string(key), value));
+    }
+    record.push('}');
+    record
+}
+
+/// Returns the strings as a JSON array.
+fn json_array(values: &[String]) -> String {
+    let values: Vec<String> = values.iter().map(|v| json_string(v)).collect();
+    format!("[{}]", values.join(","))
+}
+
+/// Returns the string as JSON, or null if there is none.
+fn json_option(value: &Option<String>) -> String {
+    value
+        .as_ref()
+        .map_or("null".to_string(), |v| json_string(v))
+}
+
+/// Returns the string as a quoted and escaped JSON string.
+fn json_string(value: &str) -> String {
+    let mut json = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::summary::{SummaryRow, TaskResult};
+
+    describe!(json_string => {
+        #[test]
+        fn escapes_special_characters(){
+            assert_eq!("\"a\\\"b\\\\c\\nd\\u0001\"", json_string("a\"b\\c\nd\u{1}"));
+        }
+    });
+
+    describe!(quote => {
+        #[test]
+        fn safe_arg_is_unchanged(){
+            assert_eq!("./src/main.rs", quote("./src/main.rs"));
+        }
+
+        #[test]
+        fn unsafe_arg_is_quoted(){
+            assert_eq!("'\"HI\"'", quote("\"HI\""));
+            assert_eq!("'it'\\''s'", quote("it's"));
+            assert_eq!("''", quote(""));
+        }
+    });
+
+    describe!(to_json => {
+        #[test]
+        fn exit(){
+            let event = Event::Exit{ task: "build".into(), pid: 7, code: 2, duration: Duration::from_millis(1500) };
+            assert_eq!(vec!["{\"type\":\"exit\",\"task\":\"build\",\"pid\":7,\"code\":2,\"duration_ms\":1500}"], event.to_json());
+        }
+
+        #[test]
+        fn output_returns_record_per_line(){
+            let event = Event::Output{ task: "build".into(), pid: 7, stream: Stream::Stderr, lines: vec!["a".into(), "b".into()] };
+
+            let expected = vec![
+                "{\"type\":\"output\",\"task\":\"build\",\"pid\":7,\"stream\":\"stderr\",\"line\":\"a\"}",
+                "{\"type\":\"output\",\"task\":\"build\",\"pid\":7,\"stream\":\"stderr\",\"line\":\"b\"}",
+            ];
+            assert_eq!(expected, event.to_json());
+        }
+
+        #[test]
+        fn start(){
+            let event = Event::Start{ task: "build".into(), pid: 7, argv: vec!["echo".into(), "a b".into()], cwd: None };
+            assert_eq!(vec!["{\"type\":\"start\",\"task\":\"build\",\"pid\":7,\"argv\":[\"echo\",\"a b\"],\"cwd\":null}"], event.to_json());
+        }
+
+        #[test]
+        fn summary(){
+            let summary = Summary{ rows: vec![
+                SummaryRow{ duration: Some(Duration::from_millis(5)), result: TaskResult::Passed, task: "build".into() },
+                SummaryRow{ duration: None, result: TaskResult::Skipped, task: "test".into() },
+            ]};
+
+            let expected = "{\"type\":\"summary\",\"passed\":false,\"tasks\":[{\"task\":\"build\",\"result\":\"passed\",\"duration_ms\":5},{\"task\":\"test\",\"result\":\"skipped\",\"duration_ms\":null}]}";
+            assert_eq!(vec![expected], Event::Summary(summary).to_json());
+        }
+
+        #[test]
+        fn triggered(){
+            let event = Event::Triggered{ task: "build".into(), path: Some("./a.rs".into()), change: Some(Change::Modified), reason: Reason::Changed };
+            assert_eq!(vec!["{\"type\":\"trigger\",\"task\":\"build\",\"path\":\"./a.rs\",\"change\":\"modified\",\"reason\":\"changed\"}"], event.to_json());
+        }
+    });
+
+    describe!(to_text => {
+        #[test]
+        fn dry_run(){
+            let event = Event::DryRun{ task: "build".into(), argv: vec!["echo".into(), "\"HI\"".into()], cwd: Some("./web".into()) };
+            assert_eq!(Some("Dry run (in ./web): echo '\"HI\"'".into()), event.to_text());
+        }
+
+        #[test]
+        fn output_shows_stdout_only(){
+            let stdout = Event::Output{ task: "build".into(), pid: 7, stream: Stream::Stdout, lines: vec!["a".into(), "b".into()] };
+            let stderr = Event::Output{ task: "build".into(), pid: 7, stream: Stream::Stderr, lines: vec!["a".into()] };
+
+            assert_eq!(Some("a\nb".into()), stdout.to_text());
+            assert_eq!(None, stderr.to_text());
+        }
+
+        #[test]
+        fn process_events_are_hidden(){
+            let event = Event::Start{ task: "build".into(), pid: 7, argv: vec![], cwd: None };
+            assert_eq!(None, event.to_text());
+        }
+
+        #[test]
+        fn warning(){
+            assert_eq!(Some("Warning: oops".into()), Event::Warning{ message: "oops".into() }.to_text());
+        }
+    });
+}
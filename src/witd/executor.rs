@@ -0,0 +1,202 @@
+#[cfg(test)]
+use std::{collections::BTreeMap, sync::Mutex};
+use std::{
+    io::{self, Write},
+    process::{self, Stdio},
+    sync::Arc,
+    thread,
+};
+
+/// A single process to run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Invocation {
+    pub argv: Vec<String>,
+    pub cwd: Option<String>,
+    /// The variables to set on top of the inherited environment.
+    pub environment: Vec<(String, String)>,
+    /// The bytes to write to stdin; stdin is closed if there are none.
+    pub stdin: Option<Vec<u8>>,
+}
+
+/// The result of running a process to completion.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcessOutput {
+    /// The exit code; a process killed by a signal exits with 1.
+    pub code: i32,
+    pub pid: u32,
+    pub stderr: Vec<u8>,
+    pub stdout: Vec<u8>,
+}
+
+/// Runs the processes for a command, so the logic deciding what to run never spawns them directly.
+pub trait Executor: Send + Sync {
+    /// Runs the invocation to completion, calling `started` with its pid once it is running.
+    /// Returns an error if the process could not be run at all.
+    fn execute(&self, invocation: &Invocation, started: &dyn Fn(u32)) -> io::Result<ProcessOutput>;
+}
+
+impl<E: Executor + ?Sized> Executor for Arc<E> {
+    fn execute(&self, invocation: &Invocation, started: &dyn Fn(u32)) -> io::Result<ProcessOutput> {
+        self.as_ref().execute(invocation, started)
+    }
+}
+
+/// Runs each invocation as a child process.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProcessExecutor;
+
+impl Executor for ProcessExecutor {
+    fn execute(&self, invocation: &Invocation, started: &dyn Fn(u32)) -> io::Result<ProcessOutput> {
+        let mut child = process::Command::new(&invocation.argv[0]);
+        if let Some(cwd) = &invocation.cwd {
+            child.current_dir(cwd);
+        }
+
+        let mut child = child
+            .args(&invocation.argv[1..])
+            .envs(invocation.environment.iter().cloned())
+            .stdin(if invocation.stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let pid = child.id();
+        started(pid);
+
+        // Write on a separate thread so a child filling its stdout can't deadlock the write.
+        let writer = match (child.stdin.take(), invocation.stdin.clone()) {
+            (Some(mut pipe), Some(bytes)) => Some(thread::spawn(move || {
+                // The child may exit without reading everything; that is not an error here.
+                let _ = pipe.write_all(&bytes);
+            })),
+            _ => None,
+        };
+
+        let output = child.wait_with_output()?;
+        if let Some(writer) = writer {
+            let _ = writer.join();
+        }
+
+        Ok(ProcessOutput {
+            // A child killed by a signal has no exit code, but still failed.
+            code: output.status.code().unwrap_or(1),
+            pid,
+            stderr: output.stderr,
+            stdout: output.stdout,
+        })
+    }
+}
+
+/// Records each invocation rather than running it, for testing what would be run.
+/// Every invocation succeeds with no output, unless an exit code was set for its program.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct RecordingExecutor {
+    exit_codes: BTreeMap<String, i32>,
+    invocations: Mutex<Vec<Invocation>>,
+}
+
+#[cfg(test)]
+impl RecordingExecutor {
+    /// Creates a new executor that has recorded nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the invocations recorded so far, in the order they were executed.
+    pub fn invocations(&self) -> Vec<Invocation> {
+        self.invocations
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Sets the exit code returned for every invocation of the given program.
+    pub fn with_exit_code(mut self, program: &str, code: i32) -> Self {
+        self.exit_codes.insert(program.to_string(), code);
+        self
+    }
+}
+
+#[cfg(test)]
+impl Executor for RecordingExecutor {
+    fn execute(&self, invocation: &Invocation, started: &dyn Fn(u32)) -> io::Result<ProcessOutput> {
+        let pid = {
+            let mut invocations = self.invocations.lock().unwrap_or_else(|e| e.into_inner());
+            invocations.push(invocation.clone());
+            invocations.len() as u32
+        };
+        started(pid);
+
+        Ok(ProcessOutput {
+            code: self
+                .exit_codes
+                .get(&invocation.argv[0])
+                .copied()
+                .unwrap_or_default(),
+            pid,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn invocation(argv: &[&str]) -> Invocation {
+        Invocation {
+            argv: argv.iter().map(|a| a.to_string()).collect(),
+            cwd: None,
+            environment: vec![],
+            stdin: None,
+        }
+    }
+
+    describe!(execute => {
+        #[test]
+        fn process_returns_output(){
+            let started = AtomicU32::new(0);
+            let mut invocation = invocation(&["sh", "-c", "cat; echo $WITD_TEST >&2; exit 3"]);
+            invocation.environment = vec![("WITD_TEST".into(), "err".into())];
+            invocation.stdin = Some(b"out".to_vec());
+
+            let output = ProcessExecutor.execute(&invocation, &|pid| started.store(pid, Ordering::Relaxed)).unwrap();
+
+            assert_eq!(started.into_inner(), output.pid);
+            assert_eq!((3, b"out".to_vec(), b"err\n".to_vec()), (output.code, output.stdout, output.stderr));
+        }
+
+        #[test]
+        fn process_in_cwd(){
+            let mut invocation = invocation(&["pwd"]);
+            invocation.cwd = Some("/".into());
+
+            assert_eq!(b"/\n".to_vec(), ProcessExecutor.execute(&invocation, &|_| {}).unwrap().stdout);
+        }
+
+        #[test]
+        fn missing_process_returns_err(){
+            let result = ProcessExecutor.execute(&invocation(&["./does/not/exist"]), &|_| panic!("started"));
+            assert_eq!(Some(io::ErrorKind::NotFound), result.err().map(|e| e.kind()));
+        }
+
+        #[test]
+        fn recording_records_invocations(){
+            let executor = RecordingExecutor::new().with_exit_code("false", 1);
+
+            let codes: Vec<i32> = [invocation(&["true"]), invocation(&["false", "a"])]
+                .iter()
+                .map(|i| executor.execute(i, &|_| {}).unwrap().code)
+                .collect();
+
+            assert_eq!(vec![0, 1], codes);
+            assert_eq!(vec![invocation(&["true"]), invocation(&["false", "a"])], executor.invocations());
+        }
+    });
+}
@@ -0,0 +1,103 @@
+use super::{Change, RunMode};
+use crate::types::File;
+use std::collections::BTreeMap;
+
+/// A unit of work for a command: the run mode to execute with and the changed files.
+/// Files run for another reason, such as a finished dependency, have no change.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Job {
+    pub changes: BTreeMap<String, Change>,
+    pub files: Vec<File>,
+    pub run_mode: RunMode,
+}
+
+impl Job {
+    /// Returns how the given files changed, exported as `WITD_EVENT`: created if every changed
+    /// file was created, otherwise modified. Returns `None` if none of the files changed.
+    pub fn change(&self, files: &[File]) -> Option<Change> {
+        let mut changes = files.iter().filter_map(|f| self.changes.get(&f.path));
+        let first = *changes.next()?;
+        Some(match changes.all(|c| *c == first) {
+            true => first,
+            false => Change::Modified,
+        })
+    }
+
+    /// Merges the other job into this one. Files from the other job replace files at the same path.
+    /// The run mode is kept, so files are never executed differently than the command says.
+    /// The first change to a file is kept, since a created file that was then modified is new to the command.
+    pub fn merge(&mut self, other: Job) {
+        for (path, change) in other.changes {
+            self.changes.entry(path).or_insert(change);
+        }
+        for file in other.files {
+            match self.files.iter_mut().find(|f| f.path == file.path) {
+                Some(existing) => *existing = file,
+                None => self.files.push(file),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::file;
+    use std::time::Duration;
+
+    describe!(change => {
+        #[test]
+        fn created_files_return_created(){
+            let job = Job{ changes: BTreeMap::from([("a".to_string(), Change::Created), ("b".to_string(), Change::Created)]), files: vec![file("a"), file("b")], run_mode: RunMode::Directory };
+            assert_eq!(Some(Change::Created), job.change(&job.files));
+        }
+
+        #[test]
+        fn any_modified_file_returns_modified(){
+            let job = Job{ changes: BTreeMap::from([("a".to_string(), Change::Created), ("b".to_string(), Change::Modified)]), files: vec![file("a"), file("b")], run_mode: RunMode::Directory };
+            assert_eq!(Some(Change::Modified), job.change(&job.files));
+            assert_eq!(Some(Change::Created), job.change(&job.files[..1]));
+        }
+
+        #[test]
+        fn unchanged_files_return_none(){
+            let job = Job{ changes: BTreeMap::new(), files: vec![file("a")], run_mode: RunMode::Directory };
+            assert_eq!(None, job.change(&job.files));
+        }
+    });
+
+    describe!(merge => {
+        #[test]
+        fn appends_new_files(){
+            let mut job = Job{ changes: BTreeMap::new(), files: vec![file("a")], run_mode: RunMode::File };
+            job.merge(Job{ changes: BTreeMap::new(), files: vec![file("b")], run_mode: RunMode::File });
+
+            assert_eq!(vec![file("a"), file("b")], job.files);
+        }
+
+        #[test]
+        fn replaces_existing_files(){
+            let mut job = Job{ changes: BTreeMap::new(), files: vec![file("a"), file("b")], run_mode: RunMode::File };
+            job.merge(Job{ changes: BTreeMap::new(), files: vec![File{ modified_at: Duration::from_secs(2), ..file("a") }], run_mode: RunMode::File });
+
+            assert_eq!(vec![File{ modified_at: Duration::from_secs(2), ..file("a") }, file("b")], job.files);
+        }
+
+        #[test]
+        fn keeps_first_change(){
+            let mut job = Job{ changes: BTreeMap::from([("a".to_string(), Change::Created)]), files: vec![file("a")], run_mode: RunMode::File };
+            job.merge(Job{ changes: BTreeMap::from([("a".to_string(), Change::Modified), ("b".to_string(), Change::Modified)]), files: vec![File{ modified_at: Duration::from_secs(2), ..file("a") }, file("b")], run_mode: RunMode::File });
+
+            let expected = BTreeMap::from([("a".to_string(), Change::Created), ("b".to_string(), Change::Modified)]);
+            assert_eq!(expected, job.changes);
+        }
+
+        #[test]
+        fn keeps_run_mode(){
+            let mut job = Job{ changes: BTreeMap::new(), files: vec![file("a")], run_mode: RunMode::File };
+            job.merge(Job{ changes: BTreeMap::new(), files: vec![file("b")], run_mode: RunMode::Directory });
+
+            assert_eq!(Job{ changes: BTreeMap::new(), files: vec![file("a"), file("b")], run_mode: RunMode::File }, job);
+        }
+    });
+}
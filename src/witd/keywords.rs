@@ -3,16 +3,87 @@ use crate::traits::PrettyPrint;
 /// The list of keywords.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Keyword {
+    After,
+    Chunk,
+    Cwd,
     Do,
     End,
+    Env,
+    EnvFile,
+    Follow,
+    In,
+    MaxDepth,
+    MinDepth,
     Mode,
+    NoHidden,
+    On,
+    Parallel,
+    Startup,
+    Stdin,
+    Task,
+    Then,
+    Where,
+    With,
 }
+
+impl Keyword {
+    /// Attempts to parse the keyword from a string.
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::values().into_iter().find(|k| k.pretty_print() == s)
+    }
+
+    /// The list of all keywords.
+    pub fn values() -> [Self; 21] {
+        [
+            Self::After,
+            Self::Chunk,
+            Self::Cwd,
+            Self::Do,
+            Self::End,
+            Self::Env,
+            Self::EnvFile,
+            Self::Follow,
+            Self::In,
+            Self::MaxDepth,
+            Self::MinDepth,
+            Self::Mode,
+            Self::NoHidden,
+            Self::On,
+            Self::Parallel,
+            Self::Startup,
+            Self::Stdin,
+            Self::Task,
+            Self::Then,
+            Self::Where,
+            Self::With,
+        ]
+    }
+}
+
 impl PrettyPrint for Keyword {
     fn pretty_print(&self) -> String {
         match self {
+            Keyword::After => "after".into(),
+            Keyword::Chunk => "chunk".into(),
+            Keyword::Cwd => "cwd".into(),
             Keyword::Do => "do".into(),
             Keyword::End => "end".into(),
+            Keyword::Env => "env".into(),
+            Keyword::EnvFile => "envfile".into(),
+            Keyword::Follow => "follow".into(),
+            Keyword::In => "in".into(),
+            Keyword::MaxDepth => "maxdepth".into(),
+            Keyword::MinDepth => "mindepth".into(),
             Keyword::Mode => "mode".into(),
+            Keyword::NoHidden => "nohidden".into(),
+            Keyword::On => "on".into(),
+            Keyword::Parallel => "parallel".into(),
+            Keyword::Startup => "startup".into(),
+            Keyword::Stdin => "stdin".into(),
+            Keyword::Task => "task".into(),
+            Keyword::Then => "then".into(),
+            Keyword::Where => "where".into(),
+            Keyword::With => "with".into(),
         }
     }
 }
@@ -21,7 +92,36 @@ impl PrettyPrint for Keyword {
 mod tests {
     use super::*;
 
+    describe!(parse => {
+        #[test]
+        fn parses_values(){
+            for keyword in Keyword::values(){
+                assert_eq!(Some(keyword), Keyword::parse(&keyword.pretty_print()));
+            }
+        }
+
+        #[test]
+        fn garbage_returns_none(){
+            assert_eq!(None, Keyword::parse("garbage"));
+        }
+    });
+
     describe!(pretty_print => {
+        #[test]
+        fn case_after(){
+            assert_eq!("after", Keyword::After.pretty_print());
+        }
+
+        #[test]
+        fn case_chunk(){
+            assert_eq!("chunk", Keyword::Chunk.pretty_print());
+        }
+
+        #[test]
+        fn case_cwd(){
+            assert_eq!("cwd", Keyword::Cwd.pretty_print());
+        }
+
         #[test]
         fn case_do(){
             assert_eq!("do", Keyword::Do.pretty_print());
@@ -32,9 +132,84 @@ mod tests {
             assert_eq!("end", Keyword::End.pretty_print());
         }
 
+        #[test]
+        fn case_env(){
+            assert_eq!("env", Keyword::Env.pretty_print());
+        }
+
+        #[test]
+        fn case_env_file(){
+            assert_eq!("envfile", Keyword::EnvFile.pretty_print());
+        }
+
+        #[test]
+        fn case_follow(){
+            assert_eq!("follow", Keyword::Follow.pretty_print());
+        }
+
+        #[test]
+        fn case_in(){
+            assert_eq!("in", Keyword::In.pretty_print());
+        }
+
+        #[test]
+        fn case_max_depth(){
+            assert_eq!("maxdepth", Keyword::MaxDepth.pretty_print());
+        }
+
+        #[test]
+        fn case_min_depth(){
+            assert_eq!("mindepth", Keyword::MinDepth.pretty_print());
+        }
+
         #[test]
         fn case_mode(){
             assert_eq!("mode", Keyword::Mode.pretty_print());
         }
+
+        #[test]
+        fn case_no_hidden(){
+            assert_eq!("nohidden", Keyword::NoHidden.pretty_print());
+        }
+
+        #[test]
+        fn case_on(){
+            assert_eq!("on", Keyword::On.pretty_print());
+        }
+
+        #[test]
+        fn case_parallel(){
+            assert_eq!("parallel", Keyword::Parallel.pretty_print());
+        }
+
+        #[test]
+        fn case_startup(){
+            assert_eq!("startup", Keyword::Startup.pretty_print());
+        }
+
+        #[test]
+        fn case_stdin(){
+            assert_eq!("stdin", Keyword::Stdin.pretty_print());
+        }
+
+        #[test]
+        fn case_task(){
+            assert_eq!("task", Keyword::Task.pretty_print());
+        }
+
+        #[test]
+        fn case_then(){
+            assert_eq!("then", Keyword::Then.pretty_print());
+        }
+
+        #[test]
+        fn case_where(){
+            assert_eq!("where", Keyword::Where.pretty_print());
+        }
+
+        #[test]
+        fn case_with(){
+            assert_eq!("with", Keyword::With.pretty_print());
+        }
     });
 }
@@ -1,68 +1,206 @@
 mod command;
+mod command_builder;
 mod command_err;
+mod event;
+mod executor;
+mod job;
 mod keywords;
+mod outcome;
+mod reporter;
 mod run_mode;
+mod run_queue;
 mod script_options;
+mod startup_mode;
+mod stdin_format;
+mod task_err;
+mod task_graph;
+mod trigger;
 
-use crate::types::File;
+use crate::{list_all_files::FileErr, state::FileState, types::File, witdfile::WitdfileErr};
 pub use command::*;
+pub use command_builder::*;
 pub use command_err::*;
+pub use event::*;
+pub use executor::*;
+pub use job::*;
 pub use keywords::*;
+pub use outcome::*;
+pub use reporter::*;
 pub use run_mode::*;
+pub use run_queue::*;
 pub use script_options::*;
-use std::collections::HashMap;
+pub use startup_mode::*;
+use std::collections::{BTreeMap, HashMap};
+pub use stdin_format::*;
+pub use task_err::*;
+pub use task_graph::*;
+pub use trigger::*;
 
 /// An error that may be returned by WITD.
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum WitdErr {
     CommandErr(CommandErr),
+    FileErr(FileErr),
+    TaskErr(TaskErr),
+    WitdfileErr(WitdfileErr),
 }
 impl From<CommandErr> for WitdErr {
     fn from(e: CommandErr) -> Self {
         Self::CommandErr(e)
     }
 }
+impl From<FileErr> for WitdErr {
+    fn from(e: FileErr) -> Self {
+        Self::FileErr(e)
+    }
+}
+impl From<TaskErr> for WitdErr {
+    fn from(e: TaskErr) -> Self {
+        Self::TaskErr(e)
+    }
+}
+impl From<WitdfileErr> for WitdErr {
+    fn from(e: WitdfileErr) -> Self {
+        Self::WitdfileErr(e)
+    }
+}
 
+/// Watches the files of a single command, detecting changes and executing the command for them.
+/// Processes are run by the executor, so detection and execution can be tested without spawning any.
 #[derive(Debug, PartialEq)]
-pub struct Witd {
+pub(crate) struct Witd<E = ProcessExecutor> {
     command: Command,
+    /// The files the command has finished with, which are the only files persisted as seen.
+    completed: BTreeMap<String, FileState>,
+    executor: E,
     files: HashMap<String, File>,
+    initialized: bool,
+    previous: Option<BTreeMap<String, FileState>>,
 }
 
 impl Witd {
-    /// Executes the given instance on the collection of files.
-    pub fn execute(&mut self, files: Vec<File>) -> Result<(), WitdErr> {
-        let mut execute_directory = false;
-        for file in files.iter() {
-            let should_execute = match self.get_file(file) {
-                Some(existing) => {
-                    if existing.is_older(file) {
+    /// Creates a new Watcher in the Deep object
+    pub(crate) fn new(command: Command) -> Self {
+        Self {
+            command,
+            completed: BTreeMap::new(),
+            executor: ProcessExecutor,
+            files: HashMap::new(),
+            initialized: false,
+            previous: None,
+        }
+    }
+}
+
+impl<E: Executor> Witd<E> {
+    /// Returns the command for the instance.
+    pub(crate) fn command(&self) -> &Command {
+        &self.command
+    }
+
+    /// Records the files of a job that finished successfully as finished with.
+    pub(crate) fn complete(&mut self, job: &Job) {
+        for file in job.files.iter() {
+            self.completed
+                .insert(file.path.clone(), FileState::from(file));
+        }
+    }
+
+    /// Records the given collection of files, returning the job to run for any changes.
+    pub(crate) fn detect(&mut self, files: Vec<File>) -> Option<Job> {
+        let mut previous = None;
+        if !self.initialized {
+            self.initialized = true;
+
+            let startup_mode = self.command.startup_mode();
+            if let Some(restored) = self.previous.take() {
+                // Files unchanged since the last session are recorded without triggering.
+                for file in files.iter() {
+                    if restored.get(&file.path) == Some(&FileState::from(file)) {
+                        self.completed
+                            .insert(file.path.clone(), FileState::from(file));
                         self.insert(file.clone());
-                        true
-                    } else {
-                        false
                     }
                 }
-                None => {
-                    self.insert(file.clone());
-                    true
+                previous = Some(restored);
+            } else if startup_mode != StartupMode::All {
+                let job = Job {
+                    changes: files
+                        .iter()
+                        .map(|f| (f.path.clone(), Change::Created))
+                        .collect(),
+                    files: files.clone(),
+                    run_mode: RunMode::Directory,
+                };
+
+                // Files skipped on startup are finished with, as nothing will ever run for them.
+                for file in files {
+                    if startup_mode == StartupMode::None {
+                        self.completed
+                            .insert(file.path.clone(), FileState::from(&file));
+                    }
+                    self.insert(file);
                 }
+
+                return match startup_mode {
+                    StartupMode::Once => Some(job),
+                    _ => None,
+                };
+            }
+        }
+
+        let mut changes = BTreeMap::new();
+        let mut changed = vec![];
+        for file in files.iter() {
+            let change = match self.get_file(file) {
+                Some(existing) => match existing.is_older(file) {
+                    true => Some(Change::Modified),
+                    false => None,
+                },
+                // Files seen in the last session were modified since, rather than created.
+                None => match previous
+                    .as_ref()
+                    .is_some_and(|p| p.contains_key(&file.path))
+                {
+                    true => Some(Change::Modified),
+                    false => Some(Change::Created),
+                },
             };
 
-            if should_execute {
-                if self.command.run_mode() == RunMode::File {
-                    self.command.execute(Some(file));
-                } else {
-                    execute_directory = true;
-                }
+            if let Some(change) = change {
+                self.insert(file.clone());
+                changes.insert(file.path.clone(), change);
+                changed.push(file.clone());
             }
         }
 
-        if execute_directory {
-            self.command.execute(None);
+        if changed.is_empty() {
+            None
+        } else {
+            Some(Job {
+                changes,
+                files: changed,
+                run_mode: self.command.run_mode(),
+            })
         }
+    }
+
+    /// Executes the command for the given job, returning whether every execution succeeded.
+    pub(crate) fn execute(&self, job: &Job, reporter: &dyn Reporter) -> bool {
+        self.command.run(job, &self.executor, reporter)
+    }
+
+    /// Returns the executor the command is executed with.
+    pub(crate) fn executor(&self) -> &E {
+        &self.executor
+    }
 
-        Ok(())
+    /// Returns every recorded file, sorted by path.
+    pub(crate) fn files(&self) -> Vec<File> {
+        let mut files: Vec<File> = self.files.values().cloned().collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        files
     }
 
     /// Attempts to retrieve the given file.
@@ -75,11 +213,27 @@ impl Witd {
         self.files.insert(file.path.clone(), file);
     }
 
-    /// Creates a new Watcher in the Deep object
-    pub fn new(command: Command) -> Self {
-        Self {
-            command,
-            files: HashMap::new(),
+    /// Restores the files recorded in a previous session, so only files changed since then trigger.
+    /// This replaces the startup mode for the initial scan.
+    pub(crate) fn restore(&mut self, files: BTreeMap<String, FileState>) {
+        self.previous = Some(files);
+    }
+
+    /// Returns the files the command has finished with, to be restored in a later session.
+    /// Files whose job is pending or failed are left out, so they trigger again next session.
+    pub(crate) fn snapshot(&self) -> BTreeMap<String, FileState> {
+        self.completed.clone()
+    }
+
+    /// Sets the executor the command is executed with.
+    pub(crate) fn with_executor<F: Executor>(self, executor: F) -> Witd<F> {
+        Witd {
+            command: self.command,
+            completed: self.completed,
+            executor,
+            files: self.files,
+            initialized: self.initialized,
+            previous: self.previous,
         }
     }
 }
@@ -87,41 +241,273 @@ impl Witd {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_helpers::file;
+    use std::time::Duration;
 
     fn cmd() -> Command {
         Command::parse("directory . do echo end").unwrap()
     }
 
+    /// Returns how each of the files changed, keyed by path.
+    fn changes(changes: &[(&str, Change)]) -> BTreeMap<String, Change> {
+        changes.iter().map(|(p, c)| (p.to_string(), *c)).collect()
+    }
+
+    /// Returns the argv of every invocation the executor recorded.
+    fn argvs(executor: &RecordingExecutor) -> Vec<Vec<String>> {
+        executor.invocations().into_iter().map(|i| i.argv).collect()
+    }
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
     describe!(execute => {
         #[test]
-        fn tests(){
-            todo!()
+        fn file_mode_executes_per_file(){
+            let command = Command::parse("foreach file in . do echo PATH end").unwrap();
+            let witd = Witd::new(command).with_executor(RecordingExecutor::new());
+
+            let job = Job{ changes: BTreeMap::new(), files: vec![file("a"), file("b")], run_mode: RunMode::File };
+            assert!(witd.execute(&job, &|_: Event| {}));
+            assert_eq!(vec![argv(&["echo", "a"]), argv(&["echo", "b"])], argvs(witd.executor()));
+        }
+
+        #[test]
+        fn directory_mode_executes_once(){
+            let command = Command::parse("foreach file in . do echo FILES end").unwrap();
+            let witd = Witd::new(command).with_executor(RecordingExecutor::new());
+
+            let job = Job{ changes: BTreeMap::new(), files: vec![file("a"), file("b")], run_mode: RunMode::Directory };
+            assert!(witd.execute(&job, &|_: Event| {}));
+            assert_eq!(vec![argv(&["echo", "a", "b"])], argvs(witd.executor()));
+        }
+
+        #[test]
+        fn writes_each_chunk_to_stdin(){
+            let command = Command::parse("directory . chunk 1 stdin lines do xargs echo end on success do echo done end").unwrap();
+            let witd = Witd::new(command).with_executor(RecordingExecutor::new());
+
+            let job = Job{ changes: BTreeMap::new(), files: vec![file("a"), file("b")], run_mode: RunMode::Directory };
+            assert!(witd.execute(&job, &|_: Event| {}));
+
+            let stdin: Vec<Option<Vec<u8>>> = witd.executor().invocations().into_iter().map(|i| i.stdin).collect();
+            assert_eq!(vec![Some(b"a\n".to_vec()), Some(b"b\n".to_vec()), None], stdin);
+        }
+
+        #[test]
+        fn sets_environment(){
+            let command = Command::parse("directory . with env A=1 do echo end").unwrap();
+            let witd = Witd::new(command).with_executor(RecordingExecutor::new());
+
+            let job = Job{ changes: changes(&[("a", Change::Created)]), files: vec![file("a")], run_mode: RunMode::Directory };
+            witd.execute(&job, &|_: Event| {});
+
+            let environment = &witd.executor().invocations()[0].environment;
+            assert!(environment.contains(&("WITD_EVENT".into(), "created".into())));
+            assert!(environment.contains(&("A".into(), "1".into())));
+        }
+
+        #[test]
+        fn sets_event_per_trigger(){
+            let command = Command::parse("foreach file in . do echo end").unwrap();
+            let witd = Witd::new(command).with_executor(RecordingExecutor::new());
+
+            let job = Job{ changes: changes(&[("a", Change::Created), ("b", Change::Modified)]), files: vec![file("a"), file("b"), file("c")], run_mode: RunMode::File };
+            witd.execute(&job, &|_: Event| {});
+
+            let events: Vec<Option<String>> = witd.executor().invocations().iter().map(|i| {
+                i.environment.iter().find(|(k, _)| k == "WITD_EVENT").map(|(_, v)| v.clone())
+            }).collect();
+            assert_eq!(vec![Some("created".into()), Some("modified".into()), None], events);
+        }
+
+        #[test]
+        fn failed_step_stops_and_runs_failure_handler(){
+            let command = Command::parse("directory . do false then echo skipped end on failure do echo EXIT_CODE end on success do echo passed end").unwrap();
+            let witd = Witd::new(command).with_executor(RecordingExecutor::new().with_exit_code("false", 2));
+
+            assert!(!witd.execute(&Job{ changes: BTreeMap::new(), files: vec![], run_mode: RunMode::Directory }, &|_: Event| {}));
+            assert_eq!(vec![argv(&["false"]), argv(&["echo", "2"])], argvs(witd.executor()));
+        }
+
+        #[test]
+        fn reports_process_events(){
+            let command = Command::parse("task build: directory . do echo end").unwrap();
+            let witd = Witd::new(command).with_executor(RecordingExecutor::new().with_exit_code("echo", 3));
+            let events = std::sync::Mutex::new(vec![]);
+
+            witd.execute(&Job{ changes: BTreeMap::new(), files: vec![], run_mode: RunMode::Directory }, &|e: Event| events.lock().unwrap().push(e));
+
+            let events = events.into_inner().unwrap();
+            assert_eq!(Event::Start{ task: "build".into(), pid: 1, argv: argv(&["echo"]), cwd: None }, events[0]);
+            assert!(matches!(events[1], Event::Exit{ pid: 1, code: 3, .. }));
+        }
+    });
+
+    describe!(detect => {
+        #[test]
+        fn new_files_return_job(){
+            let mut witd = Witd::new(cmd());
+
+            let expected = Some(Job{ changes: changes(&[("a", Change::Created), ("b", Change::Created)]), files: vec![file("a"), file("b")], run_mode: RunMode::Directory });
+            assert_eq!(expected, witd.detect(vec![file("a"), file("b")]));
+        }
+
+        #[test]
+        fn unchanged_files_return_none(){
+            let mut witd = Witd::new(cmd());
+            witd.detect(vec![file("a")]);
+
+            assert_eq!(None, witd.detect(vec![file("a")]));
+        }
+
+        #[test]
+        fn modified_files_return_job(){
+            let mut witd = Witd::new(cmd());
+            witd.detect(vec![file("a"), file("b")]);
+
+            let mut modified = file("b");
+            modified.modified_at += Duration::from_secs(1);
+
+            let expected = Some(Job{ changes: changes(&[("b", Change::Modified)]), files: vec![modified.clone()], run_mode: RunMode::Directory });
+            assert_eq!(expected, witd.detect(vec![file("a"), modified]));
+        }
+
+        #[test]
+        fn startup_once_returns_directory_job(){
+            let command = Command::parse("foreach file in . startup once do echo end").unwrap();
+            let mut witd = Witd::new(command);
+
+            let expected = Some(Job{ changes: changes(&[("a", Change::Created)]), files: vec![file("a")], run_mode: RunMode::Directory });
+            assert_eq!(expected, witd.detect(vec![file("a")]));
+            assert_eq!(None, witd.detect(vec![file("a")]));
+        }
+
+        #[test]
+        fn startup_none_returns_none(){
+            let command = Command::parse("foreach file in . startup none do echo end").unwrap();
+            let mut witd = Witd::new(command);
+
+            assert_eq!(None, witd.detect(vec![file("a")]));
+            assert_eq!(Some(&file("a")), witd.files.get("a"));
+            assert_eq!(None, witd.detect(vec![file("a")]));
+        }
+    });
+
+    describe!(restore => {
+        #[test]
+        fn only_changed_files_return_job(){
+            let command = Command::parse("foreach file in . startup once do echo end").unwrap();
+            let mut witd = Witd::new(command);
+
+            let mut modified = file("b");
+            modified.modified_at += Duration::from_secs(1);
+            witd.restore(BTreeMap::from([
+                ("a".to_string(), FileState::from(&file("a"))),
+                ("b".to_string(), FileState::from(&file("b"))),
+            ]));
+
+            let expected = Some(Job{ changes: changes(&[("b", Change::Modified), ("c", Change::Created)]), files: vec![modified.clone(), file("c")], run_mode: RunMode::File });
+            assert_eq!(expected, witd.detect(vec![file("a"), modified, file("c")]));
+        }
+    });
+
+    describe!(snapshot => {
+        #[test]
+        fn returns_completed_files(){
+            let mut witd = Witd::new(cmd());
+            let job = witd.detect(vec![file("a")]).unwrap();
+            assert_eq!(BTreeMap::new(), witd.snapshot());
+
+            witd.complete(&job);
+            let expected = BTreeMap::from([("a".to_string(), FileState::from(&file("a")))]);
+            assert_eq!(expected, witd.snapshot());
+        }
+
+        #[test]
+        fn returns_files_skipped_on_startup(){
+            let command = Command::parse("foreach file in . startup none do echo end").unwrap();
+            let mut witd = Witd::new(command);
+            witd.detect(vec![file("a")]);
+
+            let expected = BTreeMap::from([("a".to_string(), FileState::from(&file("a")))]);
+            assert_eq!(expected, witd.snapshot());
+        }
+
+        #[test]
+        fn returns_unchanged_restored_files(){
+            let mut witd = Witd::new(cmd());
+            let mut modified = file("b");
+            modified.modified_at += Duration::from_secs(1);
+            witd.restore(BTreeMap::from([
+                ("a".to_string(), FileState::from(&file("a"))),
+                ("b".to_string(), FileState::from(&file("b"))),
+            ]));
+            witd.detect(vec![file("a"), modified]);
+
+            let expected = BTreeMap::from([("a".to_string(), FileState::from(&file("a")))]);
+            assert_eq!(expected, witd.snapshot());
+        }
+    });
+
+    describe!(files => {
+        #[test]
+        fn returns_files_sorted_by_path(){
+            let mut witd = Witd::new(cmd());
+            witd.insert(file("b"));
+            witd.insert(file("a"));
+
+            assert_eq!(vec![file("a"), file("b")], witd.files());
         }
     });
 
     describe!(get_file => {
         #[test]
         fn returns_none_for_nonexistant_obj(){
-            todo!();
+            let mut witd = Witd::new(cmd());
+            witd.insert(file("a"));
+
+            assert_eq!(None, witd.get_file(&file("b")));
         }
 
         #[test]
         fn returns_some_for_existant_obj(){
-            todo!();
+            let mut witd = Witd::new(cmd());
+            witd.insert(file("a"));
+
+            let mut modified = file("a");
+            modified.modified_at += Duration::from_secs(1);
+            assert_eq!(Some(&file("a")), witd.get_file(&modified));
         }
     });
 
     describe!(insert => {
         #[test]
         fn inserts_obj(){
-            todo!();
+            let mut witd = Witd::new(cmd());
+            witd.insert(file("a"));
+
+            assert_eq!(HashMap::from([("a".to_string(), file("a"))]), witd.files);
+        }
+
+        #[test]
+        fn replaces_existing_obj(){
+            let mut witd = Witd::new(cmd());
+            let mut modified = file("a");
+            modified.modified_at += Duration::from_secs(1);
+
+            witd.insert(file("a"));
+            witd.insert(modified.clone());
+
+            assert_eq!(HashMap::from([("a".to_string(), modified)]), witd.files);
         }
     });
 
     describe!(new => {
         #[test]
         fn returns_expected(){
-            assert_eq!(Witd{ command: cmd(), files: HashMap::new() }, Witd::new(cmd()));
+            assert_eq!(Witd{ command: cmd(), completed: BTreeMap::new(), executor: ProcessExecutor, files: HashMap::new(), initialized: false, previous: None }, Witd::new(cmd()));
         }
     });
 }
@@ -0,0 +1,70 @@
+use crate::traits::PrettyPrint;
+
+/// The list of outcomes a handler may be attached to with `on`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    /// A step of the command exited unsuccessfully.
+    Failure,
+    /// Every step of the command exited successfully.
+    Success,
+}
+
+impl Outcome {
+    /// Attempts to parse the outcome from a string.
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::values().into_iter().find(|o| o.pretty_print() == s)
+    }
+
+    /// The allowed string values for the outcome.
+    pub fn values() -> [Self; 2] {
+        [Self::Failure, Self::Success]
+    }
+}
+
+impl PrettyPrint for Outcome {
+    fn pretty_print(&self) -> String {
+        match self {
+            Outcome::Failure => "failure",
+            Outcome::Success => "success",
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    describe!(parse => {
+        #[test]
+        fn parses_values(){
+            for outcome in Outcome::values(){
+                assert_eq!(Some(outcome), Outcome::parse(&outcome.pretty_print()));
+            }
+        }
+
+        #[test]
+        fn garbage_returns_none(){
+            assert_eq!(None, Outcome::parse("garbage"));
+        }
+    });
+
+    describe!(pretty_print => {
+        #[test]
+        fn failure(){
+            assert_eq!("failure", Outcome::Failure.pretty_print());
+        }
+
+        #[test]
+        fn success(){
+            assert_eq!("success", Outcome::Success.pretty_print());
+        }
+    });
+
+    describe!(values => {
+        #[test]
+        fn returns_expected(){
+            assert_eq!([Outcome::Failure, Outcome::Success], Outcome::values());
+        }
+    });
+}
@@ -0,0 +1,167 @@
+use super::Event;
+use crate::traits::PrettyPrint;
+use std::{io::Write, sync::Mutex};
+
+/// The list of formats events may be reported in.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Format {
+    /// Newline delimited JSON, one record per event.
+    Json,
+    /// Human readable text.
+    #[default]
+    Text,
+}
+
+impl Format {
+    /// Attempts to parse the format from a string.
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::values().into_iter().find(|f| f.pretty_print() == s)
+    }
+
+    /// The allowed string values for the format.
+    pub fn values() -> [Self; 2] {
+        [Self::Json, Self::Text]
+    }
+}
+
+impl PrettyPrint for Format {
+    fn pretty_print(&self) -> String {
+        match self {
+            Format::Json => "json",
+            Format::Text => "text",
+        }
+        .into()
+    }
+}
+
+/// Receives the events that occur while watching and executing.
+pub trait Reporter: Send + Sync {
+    /// Reports the given event.
+    fn report(&self, event: Event);
+}
+
+/// Any callback taking events is a reporter, so events may be handled in process.
+impl<F: Fn(Event) + Send + Sync> Reporter for F {
+    fn report(&self, event: Event) {
+        self(event)
+    }
+}
+
+/// Writes each event to a stream in the given format.
+pub struct StreamReporter {
+    format: Format,
+    stream: Mutex<Box<dyn Write + Send>>,
+}
+
+impl StreamReporter {
+    /// Creates a new reporter writing to the given stream.
+    pub fn new(format: Format, stream: Box<dyn Write + Send>) -> Self {
+        Self {
+            format,
+            stream: Mutex::new(stream),
+        }
+    }
+}
+
+impl Reporter for StreamReporter {
+    fn report(&self, event: Event) {
+        let lines = match self.format {
+            Format::Json => event.to_json(),
+            Format::Text => event.to_text().into_iter().collect(),
+        };
+
+        // Hold the lock for the whole event so its lines are never interleaved with another's.
+        // A failed write has nowhere else to be reported, so it is ignored.
+        let mut stream = self.stream.lock().unwrap_or_else(|e| e.into_inner());
+        for line in lines {
+            let _ = writeln!(stream, "{}", line);
+        }
+        let _ = stream.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io, sync::Arc};
+
+    /// A stream that can be read back after the reporter takes ownership of it.
+    #[derive(Clone, Default)]
+    struct SharedStream(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedStream {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    describe!(parse => {
+        #[test]
+        fn parses_values(){
+            for format in Format::values(){
+                assert_eq!(Some(format), Format::parse(&format.pretty_print()));
+            }
+        }
+
+        #[test]
+        fn garbage_returns_none(){
+            assert_eq!(None, Format::parse("garbage"));
+        }
+    });
+
+    describe!(pretty_print => {
+        #[test]
+        fn json(){
+            assert_eq!("json", Format::Json.pretty_print());
+        }
+
+        #[test]
+        fn text(){
+            assert_eq!("text", Format::Text.pretty_print());
+        }
+    });
+
+    describe!(report => {
+        #[test]
+        fn callback_receives_events(){
+            let events = Mutex::new(vec![]);
+            let reporter = |event: Event| events.lock().unwrap().push(event);
+
+            reporter.report(Event::Warning{ message: "a".into() });
+
+            assert_eq!(vec![Event::Warning{ message: "a".into() }], *events.lock().unwrap());
+        }
+
+        #[test]
+        fn json_writes_records(){
+            let stream = SharedStream::default();
+            let reporter = StreamReporter::new(Format::Json, Box::new(stream.clone()));
+
+            reporter.report(Event::Warning{ message: "a".into() });
+            reporter.report(Event::Warning{ message: "b".into() });
+
+            assert_eq!("{\"type\":\"warning\",\"message\":\"a\"}\n{\"type\":\"warning\",\"message\":\"b\"}\n", stream.contents());
+        }
+
+        #[test]
+        fn text_skips_hidden_events(){
+            let stream = SharedStream::default();
+            let reporter = StreamReporter::new(Format::Text, Box::new(stream.clone()));
+
+            reporter.report(Event::Scan{ task: "build".into(), files: 1, errors: 0 });
+            reporter.report(Event::Warning{ message: "a".into() });
+
+            assert_eq!("Warning: a\n", stream.contents());
+        }
+    });
+}
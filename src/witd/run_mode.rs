@@ -7,24 +7,43 @@ use super::ScriptOptions;
 pub enum RunMode {
     Directory,
     File,
+    EachDirectory,
 }
 impl RunMode {
     /// Returns the allowed script options for the run mode.
     pub fn allowed_options(&self) -> Vec<ScriptOptions> {
         match self {
-            RunMode::Directory => vec![ScriptOptions::Directory],
+            RunMode::Directory | RunMode::EachDirectory => {
+                vec![
+                    ScriptOptions::Directory,
+                    ScriptOptions::Files,
+                    ScriptOptions::Root,
+                ]
+            }
             RunMode::File => vec![
                 ScriptOptions::Directory,
                 ScriptOptions::Ext,
+                ScriptOptions::Files,
                 ScriptOptions::Name,
                 ScriptOptions::Path,
+                ScriptOptions::RelPath,
+                ScriptOptions::Root,
+                ScriptOptions::Target,
             ],
         }
     }
 
+    /// Returns the syntax that begins a command with the run mode.
+    pub fn syntax(&self) -> String {
+        match self {
+            RunMode::Directory => self.pretty_print(),
+            RunMode::File | RunMode::EachDirectory => format!("foreach {} in", self.pretty_print()),
+        }
+    }
+
     /// The allowed string values for the run mode.
-    pub fn values() -> [Self; 2] {
-        [Self::Directory, Self::File]
+    pub fn values() -> [Self; 3] {
+        [Self::Directory, Self::File, Self::EachDirectory]
     }
 }
 
@@ -33,6 +52,7 @@ impl PrettyPrint for RunMode {
         match self {
             RunMode::Directory => "directory".into(),
             RunMode::File => "file".into(),
+            RunMode::EachDirectory => "dir".into(),
         }
     }
 }
@@ -44,13 +64,19 @@ mod tests {
     describe!(allowed_options => {
         #[test]
         fn directory() {
-            let expected = vec![ScriptOptions::Directory];
+            let expected = vec![ScriptOptions::Directory, ScriptOptions::Files, ScriptOptions::Root];
             assert_eq!(expected, RunMode::Directory.allowed_options());
         }
 
+        #[test]
+        fn each_directory() {
+            let expected = vec![ScriptOptions::Directory, ScriptOptions::Files, ScriptOptions::Root];
+            assert_eq!(expected, RunMode::EachDirectory.allowed_options());
+        }
+
         #[test]
         fn file() {
-            let expected = vec![ScriptOptions::Directory, ScriptOptions::Ext, ScriptOptions::Name, ScriptOptions::Path];
+            let expected = vec![ScriptOptions::Directory, ScriptOptions::Ext, ScriptOptions::Files, ScriptOptions::Name, ScriptOptions::Path, ScriptOptions::RelPath, ScriptOptions::Root, ScriptOptions::Target];
             assert_eq!(expected, RunMode::File.allowed_options());
         }
     });
@@ -65,12 +91,34 @@ mod tests {
         fn per_file(){
             assert_eq!("file", RunMode::File.pretty_print());
         }
+
+        #[test]
+        fn per_each_directory(){
+            assert_eq!("dir", RunMode::EachDirectory.pretty_print());
+        }
+    });
+
+    describe!(syntax => {
+        #[test]
+        fn directory(){
+            assert_eq!("directory", RunMode::Directory.syntax());
+        }
+
+        #[test]
+        fn file(){
+            assert_eq!("foreach file in", RunMode::File.syntax());
+        }
+
+        #[test]
+        fn each_directory(){
+            assert_eq!("foreach dir in", RunMode::EachDirectory.syntax());
+        }
     });
 
     describe!(values => {
         #[test]
         fn returns_expected(){
-            assert_eq!([RunMode::Directory, RunMode::File], RunMode::values());
+            assert_eq!([RunMode::Directory, RunMode::File, RunMode::EachDirectory], RunMode::values());
         }
     });
 }
@@ -0,0 +1,110 @@
+use super::Job;
+
+/// A coalescing run queue for a single command. While a job is running, any number of new
+/// jobs collapse into exactly one follow-up run.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum RunQueue {
+    #[default]
+    Idle,
+    Running,
+    RunningWithRerunPending(Job),
+}
+
+impl RunQueue {
+    /// Marks the running job as finished, returning the follow-up job if one should start now.
+    pub fn finish(&mut self) -> Option<Job> {
+        match std::mem::take(self) {
+            RunQueue::Idle | RunQueue::Running => None,
+            RunQueue::RunningWithRerunPending(job) => {
+                *self = RunQueue::Running;
+                Some(job)
+            }
+        }
+    }
+
+    /// Queues the given job, returning it if it should start now.
+    pub fn push(&mut self, job: Job) -> Option<Job> {
+        match std::mem::take(self) {
+            RunQueue::Idle => {
+                *self = RunQueue::Running;
+                Some(job)
+            }
+            RunQueue::Running => {
+                *self = RunQueue::RunningWithRerunPending(job);
+                None
+            }
+            RunQueue::RunningWithRerunPending(mut pending) => {
+                pending.merge(job);
+                *self = RunQueue::RunningWithRerunPending(pending);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_helpers::file, witd::RunMode};
+    use std::collections::BTreeMap;
+
+    fn job(paths: &[&str]) -> Job {
+        Job {
+            changes: BTreeMap::new(),
+            files: paths.iter().map(|p| file(p)).collect(),
+            run_mode: RunMode::Directory,
+        }
+    }
+
+    describe!(finish => {
+        #[test]
+        fn idle_stays_idle(){
+            let mut queue = RunQueue::Idle;
+            assert_eq!(None, queue.finish());
+            assert_eq!(RunQueue::Idle, queue);
+        }
+
+        #[test]
+        fn running_becomes_idle(){
+            let mut queue = RunQueue::Running;
+            assert_eq!(None, queue.finish());
+            assert_eq!(RunQueue::Idle, queue);
+        }
+
+        #[test]
+        fn pending_starts_rerun(){
+            let mut queue = RunQueue::RunningWithRerunPending(job(&["a"]));
+            assert_eq!(Some(job(&["a"])), queue.finish());
+            assert_eq!(RunQueue::Running, queue);
+        }
+    });
+
+    describe!(push => {
+        #[test]
+        fn idle_starts_job(){
+            let mut queue = RunQueue::Idle;
+            assert_eq!(Some(job(&["a"])), queue.push(job(&["a"])));
+            assert_eq!(RunQueue::Running, queue);
+        }
+
+        #[test]
+        fn running_queues_rerun(){
+            let mut queue = RunQueue::Running;
+            assert_eq!(None, queue.push(job(&["a"])));
+            assert_eq!(RunQueue::RunningWithRerunPending(job(&["a"])), queue);
+        }
+
+        #[test]
+        fn pending_coalesces_into_single_rerun(){
+            let mut queue = RunQueue::Running;
+            queue.push(job(&["a"]));
+            queue.push(job(&["b"]));
+            queue.push(job(&["a"]));
+
+            assert_eq!(RunQueue::RunningWithRerunPending(job(&["a", "b"])), queue);
+            assert_eq!(Some(job(&["a", "b"])), queue.finish());
+            assert_eq!(None, queue.finish());
+            assert_eq!(RunQueue::Idle, queue);
+        }
+    });
+}
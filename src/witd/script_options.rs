@@ -3,15 +3,43 @@ use crate::traits::PrettyPrint;
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum ScriptOptions {
     Directory,
+    ExitCode,
     Ext,
+    Files,
     Name,
     Path,
+    RelPath,
+    Root,
+    Target,
 }
 
 impl ScriptOptions {
+    /// Returns the script options a command's files may be filtered by with `where`.
+    pub fn filters() -> [Self; 7] {
+        [
+            Self::Directory,
+            Self::Ext,
+            Self::Name,
+            Self::Path,
+            Self::RelPath,
+            Self::Root,
+            Self::Target,
+        ]
+    }
+
     /// Returns the values for the script options.
-    pub fn values() -> [Self; 4] {
-        [Self::Directory, Self::Ext, Self::Name, Self::Path]
+    pub fn values() -> [Self; 9] {
+        [
+            Self::Directory,
+            Self::ExitCode,
+            Self::Ext,
+            Self::Files,
+            Self::Name,
+            Self::Path,
+            Self::RelPath,
+            Self::Root,
+            Self::Target,
+        ]
     }
 }
 
@@ -19,9 +47,14 @@ impl PrettyPrint for ScriptOptions {
     fn pretty_print(&self) -> String {
         match self {
             ScriptOptions::Directory => "DIR",
+            ScriptOptions::ExitCode => "EXIT_CODE",
             ScriptOptions::Ext => "EXT",
+            ScriptOptions::Files => "FILES",
             ScriptOptions::Name => "NAME",
             ScriptOptions::Path => "PATH",
+            ScriptOptions::RelPath => "RELPATH",
+            ScriptOptions::Root => "ROOT",
+            ScriptOptions::Target => "TARGET",
         }
         .into()
     }
@@ -31,18 +64,36 @@ impl PrettyPrint for ScriptOptions {
 mod tests {
     use super::*;
 
+    describe!(filters => {
+        #[test]
+        fn excludes_options_without_file_values() {
+            assert!(!ScriptOptions::filters().contains(&ScriptOptions::ExitCode));
+            assert!(!ScriptOptions::filters().contains(&ScriptOptions::Files));
+        }
+    });
+
     describe!(pretty_print => {
         #[test]
         fn directory() {
             assert_eq!("DIR", ScriptOptions::Directory.pretty_print());
         }
 
+        #[test]
+        fn exit_code() {
+            assert_eq!("EXIT_CODE", ScriptOptions::ExitCode.pretty_print());
+        }
+
         #[test]
         fn ext() {
             assert_eq!("EXT", ScriptOptions::Ext.pretty_print());
         }
 
 
+        #[test]
+        fn files() {
+            assert_eq!("FILES", ScriptOptions::Files.pretty_print());
+        }
+
         #[test]
         fn name() {
             assert_eq!("NAME", ScriptOptions::Name.pretty_print());
@@ -52,12 +103,27 @@ mod tests {
         fn path() {
             assert_eq!("PATH", ScriptOptions::Path.pretty_print());
         }
+
+        #[test]
+        fn rel_path() {
+            assert_eq!("RELPATH", ScriptOptions::RelPath.pretty_print());
+        }
+
+        #[test]
+        fn root() {
+            assert_eq!("ROOT", ScriptOptions::Root.pretty_print());
+        }
+
+        #[test]
+        fn target() {
+            assert_eq!("TARGET", ScriptOptions::Target.pretty_print());
+        }
     });
 
     describe!(values => {
         #[test]
         fn values() {
-            assert_eq!([ScriptOptions::Directory, ScriptOptions::Ext, ScriptOptions::Name, ScriptOptions::Path], ScriptOptions::values());
+            assert_eq!([ScriptOptions::Directory, ScriptOptions::ExitCode, ScriptOptions::Ext, ScriptOptions::Files, ScriptOptions::Name, ScriptOptions::Path, ScriptOptions::RelPath, ScriptOptions::Root, ScriptOptions::Target], ScriptOptions::values());
         }
     });
 }
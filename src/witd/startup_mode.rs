@@ -0,0 +1,86 @@
+use crate::traits::PrettyPrint;
+
+/// The list of startup modes. Determines what happens to the files found on the initial scan.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StartupMode {
+    /// Every existing file is treated as new and triggers the command.
+    #[default]
+    All,
+    /// Existing files are recorded but nothing is executed; only later changes trigger.
+    None,
+    /// Existing files are recorded and the command is executed once in directory mode.
+    Once,
+}
+
+impl StartupMode {
+    /// Attempts to parse the startup mode from a string.
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::values().into_iter().find(|m| m.pretty_print() == s)
+    }
+
+    /// The allowed string values for the startup mode.
+    pub fn values() -> [Self; 3] {
+        [Self::All, Self::None, Self::Once]
+    }
+}
+
+impl PrettyPrint for StartupMode {
+    fn pretty_print(&self) -> String {
+        match self {
+            StartupMode::All => "all",
+            StartupMode::None => "none",
+            StartupMode::Once => "once",
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    describe!(default => {
+        #[test]
+        fn returns_all(){
+            assert_eq!(StartupMode::All, StartupMode::default());
+        }
+    });
+
+    describe!(parse => {
+        #[test]
+        fn parses_values(){
+            for mode in StartupMode::values(){
+                assert_eq!(Some(mode), StartupMode::parse(&mode.pretty_print()));
+            }
+        }
+
+        #[test]
+        fn garbage_returns_none(){
+            assert_eq!(None, StartupMode::parse("garbage"));
+        }
+    });
+
+    describe!(pretty_print => {
+        #[test]
+        fn all(){
+            assert_eq!("all", StartupMode::All.pretty_print());
+        }
+
+        #[test]
+        fn none(){
+            assert_eq!("none", StartupMode::None.pretty_print());
+        }
+
+        #[test]
+        fn once(){
+            assert_eq!("once", StartupMode::Once.pretty_print());
+        }
+    });
+
+    describe!(values => {
+        #[test]
+        fn returns_expected(){
+            assert_eq!([StartupMode::All, StartupMode::None, StartupMode::Once], StartupMode::values());
+        }
+    });
+}
@@ -0,0 +1,90 @@
+use crate::traits::PrettyPrint;
+
+/// The list of formats changed paths may be written to a command's stdin in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StdinFormat {
+    /// Each path is followed by a newline.
+    Lines,
+    /// Each path is followed by a NUL byte, e.g. for `xargs -0`.
+    Nul,
+}
+
+impl StdinFormat {
+    /// Attempts to parse the stdin format from a string.
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::values().into_iter().find(|f| f.pretty_print() == s)
+    }
+
+    /// Returns the separator that follows each path.
+    pub fn separator(&self) -> u8 {
+        match self {
+            StdinFormat::Lines => b'\n',
+            StdinFormat::Nul => b'\0',
+        }
+    }
+
+    /// The allowed string values for the stdin format.
+    pub fn values() -> [Self; 2] {
+        [Self::Lines, Self::Nul]
+    }
+}
+
+impl PrettyPrint for StdinFormat {
+    fn pretty_print(&self) -> String {
+        match self {
+            StdinFormat::Lines => "lines",
+            StdinFormat::Nul => "nul",
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    describe!(parse => {
+        #[test]
+        fn parses_values(){
+            for format in StdinFormat::values(){
+                assert_eq!(Some(format), StdinFormat::parse(&format.pretty_print()));
+            }
+        }
+
+        #[test]
+        fn garbage_returns_none(){
+            assert_eq!(None, StdinFormat::parse("garbage"));
+        }
+    });
+
+    describe!(pretty_print => {
+        #[test]
+        fn lines(){
+            assert_eq!("lines", StdinFormat::Lines.pretty_print());
+        }
+
+        #[test]
+        fn nul(){
+            assert_eq!("nul", StdinFormat::Nul.pretty_print());
+        }
+    });
+
+    describe!(separator => {
+        #[test]
+        fn lines(){
+            assert_eq!(b'\n', StdinFormat::Lines.separator());
+        }
+
+        #[test]
+        fn nul(){
+            assert_eq!(b'\0', StdinFormat::Nul.separator());
+        }
+    });
+
+    describe!(values => {
+        #[test]
+        fn returns_expected(){
+            assert_eq!([StdinFormat::Lines, StdinFormat::Nul], StdinFormat::values());
+        }
+    });
+}
@@ -0,0 +1,46 @@
+use crate::traits::PrettyPrint;
+
+/// An error that may occur when resolving the dependencies between tasks.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TaskErr {
+    Cycle(Vec<String>),
+    DuplicateName(String),
+    UnknownDependency { task: String, dependency: String },
+}
+
+impl PrettyPrint for TaskErr {
+    fn pretty_print(&self) -> String {
+        match self {
+            TaskErr::Cycle(tasks) => {
+                format!("Tasks depend on each other: [{}].", tasks.join(", "))
+            }
+            TaskErr::DuplicateName(name) => format!("Task '{}' is defined more than once!", name),
+            TaskErr::UnknownDependency { task, dependency } => {
+                format!("Task '{}' depends on unknown task '{}'!", task, dependency)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    describe!(pretty_print => {
+        #[test]
+        fn cycle(){
+            assert_eq!("Tasks depend on each other: [a, b].", TaskErr::Cycle(vec!["a".into(), "b".into()]).pretty_print());
+        }
+
+        #[test]
+        fn duplicate_name(){
+            assert_eq!("Task 'a' is defined more than once!", TaskErr::DuplicateName("a".into()).pretty_print());
+        }
+
+        #[test]
+        fn unknown_dependency(){
+            let e = TaskErr::UnknownDependency{ task: "test".into(), dependency: "build".into() };
+            assert_eq!("Task 'test' depends on unknown task 'build'!", e.pretty_print());
+        }
+    });
+}
@@ -0,0 +1,137 @@
+use super::{Command, TaskErr};
+use std::collections::HashMap;
+
+/// A command along with the indices of the tasks it depends on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Task {
+    pub command: Command,
+    pub dependencies: Vec<usize>,
+}
+
+/// Sorts the commands so every task comes after the tasks it depends on, keeping the
+/// original order otherwise. Dependencies are returned as indices into the sorted tasks.
+pub fn sort_tasks(commands: Vec<Command>) -> Result<Vec<Task>, TaskErr> {
+    let mut names = HashMap::new();
+    for (i, command) in commands.iter().enumerate() {
+        if let Some(name) = command.name() {
+            if names.insert(name.clone(), i).is_some() {
+                return Err(TaskErr::DuplicateName(name.clone()));
+            }
+        }
+    }
+
+    let mut dependencies = vec![];
+    for command in commands.iter() {
+        let mut indices = vec![];
+        for dependency in command.dependencies() {
+            match names.get(dependency) {
+                Some(i) => indices.push(*i),
+                None => {
+                    return Err(TaskErr::UnknownDependency {
+                        task: command.name().cloned().unwrap_or_default(),
+                        dependency: dependency.clone(),
+                    })
+                }
+            }
+        }
+        dependencies.push(indices);
+    }
+
+    // Repeatedly take the first command whose dependencies have all been taken.
+    let mut order: Vec<usize> = vec![];
+    while order.len() < commands.len() {
+        let next = (0..commands.len())
+            .find(|i| !order.contains(i) && dependencies[*i].iter().all(|d| order.contains(d)));
+
+        match next {
+            Some(i) => order.push(i),
+            None => {
+                return Err(TaskErr::Cycle(
+                    (0..commands.len())
+                        .filter(|i| !order.contains(i))
+                        .filter_map(|i| commands[i].name().cloned())
+                        .collect(),
+                ))
+            }
+        }
+    }
+
+    let mut commands: Vec<Option<Command>> = commands.into_iter().map(Some).collect();
+    Ok(order
+        .iter()
+        .map(|i| Task {
+            command: commands[*i].take().unwrap_or_else(|| unreachable!()),
+            dependencies: dependencies[*i]
+                .iter()
+                .map(|d| order.iter().position(|o| o == d).unwrap_or_default())
+                .collect(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmd(s: &str) -> Command {
+        Command::parse(s).unwrap()
+    }
+
+    describe!(sort_tasks => {
+        #[test]
+        fn keeps_order_without_tasks(){
+            let commands = vec![cmd("directory a do echo end"), cmd("directory b do echo end")];
+
+            let expected = Ok(vec![
+                Task{ command: commands[0].clone(), dependencies: vec![] },
+                Task{ command: commands[1].clone(), dependencies: vec![] },
+            ]);
+            assert_eq!(expected, sort_tasks(commands));
+        }
+
+        #[test]
+        fn sorts_dependencies_first(){
+            let commands = vec![
+                cmd("task test after build: directory . do cargo test end"),
+                cmd("directory docs do echo end"),
+                cmd("task build: directory . do cargo build end"),
+            ];
+
+            let expected = Ok(vec![
+                Task{ command: commands[1].clone(), dependencies: vec![] },
+                Task{ command: commands[2].clone(), dependencies: vec![] },
+                Task{ command: commands[0].clone(), dependencies: vec![1] },
+            ]);
+            assert_eq!(expected, sort_tasks(commands));
+        }
+
+        #[test]
+        fn duplicate_name_returns_err(){
+            let commands = vec![
+                cmd("task build: directory . do cargo build end"),
+                cmd("task build: directory . do cargo build end"),
+            ];
+
+            assert_eq!(Err(TaskErr::DuplicateName("build".into())), sort_tasks(commands));
+        }
+
+        #[test]
+        fn unknown_dependency_returns_err(){
+            let commands = vec![cmd("task test after build: directory . do cargo test end")];
+
+            let expected = Err(TaskErr::UnknownDependency{ task: "test".into(), dependency: "build".into() });
+            assert_eq!(expected, sort_tasks(commands));
+        }
+
+        #[test]
+        fn cycle_returns_err(){
+            let commands = vec![
+                cmd("task a after c: directory . do echo end"),
+                cmd("task b: directory . do echo end"),
+                cmd("task c after a: directory . do echo end"),
+            ];
+
+            assert_eq!(Err(TaskErr::Cycle(vec!["a".into(), "c".into()])), sort_tasks(commands));
+        }
+    });
+}
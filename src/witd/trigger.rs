@@ -0,0 +1,49 @@
+use crate::types::File;
+
+/// What caused a command to be executed, along with the changed files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trigger<'a> {
+    /// The root path as a whole.
+    Root(&'a [File]),
+    /// A directory containing changed files.
+    Directory(&'a str, &'a [File]),
+    /// A single changed file.
+    File(&'a File),
+}
+
+impl<'a> Trigger<'a> {
+    /// Returns the changed files for the trigger.
+    pub fn files(&self) -> &'a [File] {
+        match self {
+            Trigger::Root(files) => files,
+            Trigger::Directory(_, files) => files,
+            Trigger::File(file) => std::slice::from_ref(*file),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::file;
+
+    describe!(files => {
+        #[test]
+        fn root_returns_files(){
+            let files = vec![file("a"), file("b")];
+            assert_eq!(&files[..], Trigger::Root(&files).files());
+        }
+
+        #[test]
+        fn directory_returns_files(){
+            let files = vec![file("a"), file("b")];
+            assert_eq!(&files[..], Trigger::Directory(".", &files).files());
+        }
+
+        #[test]
+        fn file_returns_file(){
+            let f = file("a");
+            assert_eq!(std::slice::from_ref(&f), Trigger::File(&f).files());
+        }
+    });
+}
@@ -0,0 +1,322 @@
+use crate::{
+    env_file,
+    traits::PrettyPrint,
+    witd::{Command, CommandErr, Keyword, RunMode},
+};
+use std::{fs, io, path::Path};
+
+/// The name of the file that is loaded when no script is provided.
+pub const DEFAULT_PATH: &str = "Witdfile";
+
+/// An error that may occur when loading a Witdfile.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WitdfileErr {
+    Command {
+        path: String,
+        line: usize,
+        column: usize,
+        err: CommandErr,
+    },
+    Read {
+        path: String,
+        kind: io::ErrorKind,
+    },
+}
+
+impl PrettyPrint for WitdfileErr {
+    fn pretty_print(&self) -> String {
+        match self {
+            WitdfileErr::Command {
+                path,
+                line,
+                column,
+                err,
+            } => format!("{}:{}:{}: {}", path, line, column, err.pretty_print()),
+            WitdfileErr::Read { path, kind } => format!("Unable to read '{}': {}", path, kind),
+        }
+    }
+}
+
+/// A single command within a Witdfile, joined onto one line, along with where each of its
+/// lines starts.
+#[derive(Clone, Debug, PartialEq)]
+struct Block {
+    input: String,
+    lines: Vec<Line>,
+}
+
+impl Block {
+    /// Returns the line and column in the Witdfile of the byte offset into the input.
+    fn locate(&self, offset: usize) -> (usize, usize) {
+        let line = self
+            .lines
+            .iter()
+            .rev()
+            .find(|l| l.offset <= offset)
+            .unwrap_or_else(|| unreachable!("every block has a line starting at offset zero"));
+
+        (line.line, line.column + offset - line.offset)
+    }
+}
+
+/// Where a line of a block starts, both in the Witdfile and in the block's input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Line {
+    column: usize,
+    line: usize,
+    offset: usize,
+}
+
+/// Loads the commands from the Witdfile at the given path.
+/// A `.env` next to the Witdfile is used by any command that doesn't specify an `envfile`.
+pub fn load(path: &str) -> Result<Vec<Command>, WitdfileErr> {
+    let contents = fs::read_to_string(path).map_err(|e| WitdfileErr::Read {
+        path: path.to_string(),
+        kind: e.kind(),
+    })?;
+
+    let commands = parse(path, &contents)?;
+
+    let env_file = Path::new(path).with_file_name(env_file::DEFAULT_PATH);
+    if !env_file.is_file() {
+        return Ok(commands);
+    }
+
+    Ok(commands
+        .into_iter()
+        .map(|c| c.with_default_env_file(env_file.display().to_string()))
+        .collect())
+}
+
+/// Parses the commands from the contents of a Witdfile.
+pub fn parse(path: &str, contents: &str) -> Result<Vec<Command>, WitdfileErr> {
+    blocks(contents)
+        .into_iter()
+        .map(|block| {
+            Command::parse_at(&block.input).map_err(|(err, offset)| {
+                let (line, column) = block.locate(offset);
+                WitdfileErr::Command {
+                    path: path.to_string(),
+                    line,
+                    column,
+                    err,
+                }
+            })
+        })
+        .collect()
+}
+
+/// Splits the contents into blocks with comments removed. A block begins on any line that
+/// starts with a run mode or task and may span as many lines as needed.
+fn blocks(contents: &str) -> Vec<Block> {
+    let mut blocks: Vec<Block> = vec![];
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = strip_comment(line);
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let column = line.len() - line.trim_start().len() + 1;
+        match blocks.last_mut() {
+            // A task header may be followed by its command on the next line.
+            Some(block) if !starts_block(trimmed) || block.input.ends_with(':') => {
+                block.input.push(' ');
+                block.lines.push(Line {
+                    column,
+                    line: i + 1,
+                    offset: block.input.len(),
+                });
+                block.input.push_str(trimmed);
+            }
+            _ => blocks.push(Block {
+                input: trimmed.to_string(),
+                lines: vec![Line {
+                    column,
+                    line: i + 1,
+                    offset: 0,
+                }],
+            }),
+        }
+    }
+
+    blocks
+}
+
+/// Returns whether the line begins a new command or task.
+fn starts_block(line: &str) -> bool {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.first() == Some(&Keyword::Task.pretty_print().as_str()) {
+        return true;
+    }
+
+    RunMode::values().iter().any(|m| {
+        let syntax = m.syntax();
+        let syntax: Vec<&str> = syntax.split_whitespace().collect();
+        tokens.starts_with(&syntax)
+    })
+}
+
+/// Removes a `#` comment from the line. Only a `#` at the start of a token begins a comment.
+fn strip_comment(line: &str) -> &str {
+    let mut previous = None;
+    for (i, c) in line.char_indices() {
+        if c == '#' && previous.is_none_or(char::is_whitespace) {
+            return &line[..i];
+        }
+        previous = Some(c);
+    }
+
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(line: usize, column: usize, offset: usize) -> Line {
+        Line {
+            column,
+            line,
+            offset,
+        }
+    }
+
+    describe!(blocks => {
+        #[test]
+        fn single_line_blocks(){
+            let contents = "directory . do echo end\nforeach file in ./src do echo PATH end";
+
+            let expected = vec![
+                Block{ input: "directory . do echo end".into(), lines: vec![line(1, 1, 0)] },
+                Block{ input: "foreach file in ./src do echo PATH end".into(), lines: vec![line(2, 1, 0)] },
+            ];
+            assert_eq!(expected, blocks(contents));
+        }
+
+        #[test]
+        fn multi_line_blocks_with_comments(){
+            let contents = "# Formats everything\n\n  foreach file in ./src # the sources\n    nohidden\n  do\n    rustfmt PATH\n  end\n\ndirectory . do echo # done end";
+
+            let expected = vec![
+                Block{ input: "foreach file in ./src nohidden do rustfmt PATH end".into(), lines: vec![line(3, 3, 0), line(4, 5, 22), line(5, 3, 31), line(6, 5, 34), line(7, 3, 47)] },
+                Block{ input: "directory . do echo".into(), lines: vec![line(9, 1, 0)] },
+            ];
+            assert_eq!(expected, blocks(contents));
+        }
+
+        #[test]
+        fn task_blocks(){
+            let contents = "task build:\n  foreach file in ./src do cargo build end\ntask test after build:\n  directory . do cargo test end";
+
+            let expected = vec![
+                Block{ input: "task build: foreach file in ./src do cargo build end".into(), lines: vec![line(1, 1, 0), line(2, 3, 12)] },
+                Block{ input: "task test after build: directory . do cargo test end".into(), lines: vec![line(3, 1, 0), line(4, 3, 23)] },
+            ];
+            assert_eq!(expected, blocks(contents));
+        }
+
+        #[test]
+        fn empty_contents_return_empty(){
+            assert_eq!(Vec::<Block>::new(), blocks("# nothing to see here\n\n"));
+        }
+    });
+
+    describe!(locate => {
+        #[test]
+        fn returns_line_and_column_of_offset(){
+            let block = Block{ input: "directory . do echo end".into(), lines: vec![line(3, 5, 0), line(4, 3, 12)] };
+
+            assert_eq!((3, 5), block.locate(0));
+            assert_eq!((3, 15), block.locate(10));
+            assert_eq!((4, 3), block.locate(12));
+            assert_eq!((4, 14), block.locate(23));
+        }
+    });
+
+    describe!(load => {
+        #[test]
+        fn discovers_env_file(){
+            let root = std::env::temp_dir().join(format!("witd-witdfile-env-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            fs::write(root.join(DEFAULT_PATH), "directory . do echo end\ndirectory . envfile ./other.env do echo end").unwrap();
+            fs::write(root.join(env_file::DEFAULT_PATH), "A=1").unwrap();
+
+            let path = root.join(DEFAULT_PATH).display().to_string();
+            let env_file = root.join(env_file::DEFAULT_PATH).display().to_string();
+            let actual = load(&path);
+            let _ = fs::remove_dir_all(&root);
+
+            let expected = Ok(vec![
+                Command::parse("directory . do echo end").unwrap().with_default_env_file(env_file),
+                Command::parse("directory . envfile ./other.env do echo end").unwrap(),
+            ]);
+            assert_eq!(expected, actual);
+        }
+    });
+
+    describe!(parse => {
+        #[test]
+        fn parses_commands(){
+            let contents = "directory .\n  do echo\nend\n\nforeach dir in ./crates do cargo build end";
+
+            let expected = Ok(vec![
+                Command::parse("directory . do echo end").unwrap(),
+                Command::parse("foreach dir in ./crates do cargo build end").unwrap(),
+            ]);
+            assert_eq!(expected, parse("Witdfile", contents));
+        }
+
+        #[test]
+        fn reports_missing_end_where_the_command_stops(){
+            let contents = "directory . do echo end\n\n    foreach file in ./src do\n      echo PATH\n";
+
+            let expected = Err(WitdfileErr::Command{ path: "Witdfile".into(), line: 4, column: 16, err: CommandErr::MissingKeywordEnd });
+            assert_eq!(expected, parse("Witdfile", contents));
+        }
+
+        #[test]
+        fn reports_location_of_failing_clause(){
+            let contents = "directory .\n  nohidden\n  chunk 0\n  do echo end";
+
+            let expected = Err(WitdfileErr::Command{ path: "Witdfile".into(), line: 3, column: 3, err: CommandErr::InvalidChunkSize });
+            assert_eq!(expected, parse("Witdfile", contents));
+        }
+
+        #[test]
+        fn text_before_first_command_is_reported(){
+            let contents = "garbage\ndirectory . do echo end";
+
+            let expected = Err(WitdfileErr::Command{ path: "Witdfile".into(), line: 1, column: 1, err: CommandErr::MissingRunMode });
+            assert_eq!(expected, parse("Witdfile", contents));
+        }
+    });
+
+    describe!(pretty_print => {
+        #[test]
+        fn command(){
+            let e = WitdfileErr::Command{ path: "Witdfile".into(), line: 3, column: 5, err: CommandErr::MissingKeywordEnd };
+            assert_eq!("Witdfile:3:5: Missing 'end'!", e.pretty_print());
+        }
+
+        #[test]
+        fn read(){
+            let e = WitdfileErr::Read{ path: "Witdfile".into(), kind: io::ErrorKind::NotFound };
+            assert_eq!(format!("Unable to read 'Witdfile': {}", io::ErrorKind::NotFound), e.pretty_print());
+        }
+    });
+
+    describe!(strip_comment => {
+        #[test]
+        fn removes_comment(){
+            assert_eq!("do echo ", strip_comment("do echo # a comment"));
+        }
+
+        #[test]
+        fn keeps_hash_inside_token(){
+            assert_eq!("do echo a#b ", strip_comment("do echo a#b # a comment"));
+        }
+    });
+}